@@ -1,9 +1,16 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Use a fixed seed, such that results are stable between runs.
+fn seeded_rng() -> StdRng {
+    StdRng::seed_from_u64(42)
+}
 
 fn criterion_waters_benchmark(criterion: &mut Criterion) {
     use ibe::waters::*;
+    use irmaseal_curve::{G1Affine, G1Projective};
 
-    let mut rng = rand::thread_rng();
+    let mut rng = seeded_rng();
 
     let id = "email:w.geraedts@sarif.nl".as_bytes();
     let kid = Identity::derive(id);
@@ -12,35 +19,119 @@ fn criterion_waters_benchmark(criterion: &mut Criterion) {
 
     let (pk, sk) = setup(&mut rng);
     let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+    let pusk = usk.prepare();
 
     let c = encrypt(&pk, &kid, &m, &mut rng);
 
+    let usks: Vec<UserSecretKey> = (0..16)
+        .map(|i| {
+            let v = Identity::derive_str(&format!("email:user{}@sarif.nl", i));
+            extract_usk(&pk, &sk, &v, &mut rng)
+        })
+        .collect();
+    let usks2 = usks.clone();
+
+    let pk_bytes = pk.to_bytes();
+    let usk_bytes = usk.to_bytes();
+    let c_bytes = c.to_bytes();
+
     criterion.bench_function("waters generate message", |b| {
-        let mut rng = rand::thread_rng();
+        let mut rng = seeded_rng();
         b.iter(|| Message::generate(&mut rng))
     });
     criterion.bench_function("waters setup", |b| {
-        let mut rng = rand::thread_rng();
+        let mut rng = seeded_rng();
         b.iter(|| setup(&mut rng))
     });
+    criterion.bench_function("waters normalize parameters individually", |b| {
+        let mut rng = seeded_rng();
+        let ps: Vec<G1Projective> = (0..256)
+            .map(|_| G1Projective::generator() * ibe::random_scalar(&mut rng))
+            .collect();
+        b.iter(|| {
+            black_box(&ps)
+                .iter()
+                .map(G1Affine::from)
+                .collect::<Vec<_>>()
+        })
+    });
+    criterion.bench_function("waters normalize parameters batched", |b| {
+        let mut rng = seeded_rng();
+        let ps: Vec<G1Projective> = (0..256)
+            .map(|_| G1Projective::generator() * ibe::random_scalar(&mut rng))
+            .collect();
+        b.iter(|| {
+            let mut res = vec![G1Affine::identity(); ps.len()];
+            G1Projective::batch_normalize(black_box(&ps), &mut res);
+            res
+        })
+    });
     criterion.bench_function("waters derive", move |b| b.iter(|| Identity::derive(id)));
     criterion.bench_function("waters extract", move |b| {
-        let mut rng = rand::thread_rng();
+        let mut rng = seeded_rng();
         b.iter(|| extract_usk(black_box(&pk), black_box(&sk), black_box(&kid), &mut rng))
     });
     criterion.bench_function("waters encrypt", move |b| {
-        let mut rng = rand::thread_rng();
+        let mut rng = seeded_rng();
         b.iter(|| encrypt(black_box(&pk), black_box(&kid), black_box(&m), &mut rng))
     });
+    criterion.bench_function("waters entangle", move |b| {
+        b.iter(|| black_box(&pk).entangle_identity(black_box(&kid)))
+    });
+    criterion.bench_function("waters entangle with tables", move |b| {
+        let tables = pk.precompute_tables();
+        b.iter(|| black_box(&tables).entangle_identity(black_box(&kid)))
+    });
+    criterion.bench_function("waters prepare", move |b| {
+        b.iter(|| black_box(&pk).prepare())
+    });
+    criterion.bench_function("waters encrypt prepared", move |b| {
+        let mut rng = seeded_rng();
+        let ppk = pk.prepare();
+        b.iter(|| encrypt_prepared(black_box(&ppk), black_box(&kid), black_box(&m), &mut rng))
+    });
+    criterion.bench_function("waters encrypt compact", move |b| {
+        let mut rng = seeded_rng();
+        let cpk = pk.compress_parameters();
+        b.iter(|| encrypt_compact(black_box(&cpk), black_box(&kid), black_box(&m), &mut rng))
+    });
     criterion.bench_function("waters decrypt", move |b| {
         b.iter(|| decrypt(black_box(&usk), black_box(&c)))
     });
+    criterion.bench_function("waters decrypt prepared", move |b| {
+        b.iter(|| decrypt_prepared(black_box(&pusk), black_box(&c)))
+    });
+    criterion.bench_function("waters trial decrypt 16", move |b| {
+        b.iter(|| {
+            usks.iter()
+                .map(|usk| decrypt(black_box(usk), black_box(&c)))
+                .collect::<Vec<_>>()
+        })
+    });
+    criterion.bench_function("waters trial decrypt 16 prepared ciphertext", move |b| {
+        b.iter(|| {
+            let pc = black_box(&c).prepare();
+            usks2
+                .iter()
+                .map(|usk| decrypt_prepared_ciphertext(black_box(usk), &pc))
+                .collect::<Vec<_>>()
+        })
+    });
+    criterion.bench_function("waters public key from bytes", move |b| {
+        b.iter(|| PublicKey::from_bytes(black_box(&pk_bytes)))
+    });
+    criterion.bench_function("waters user secret key from bytes", move |b| {
+        b.iter(|| UserSecretKey::from_bytes(black_box(&usk_bytes)))
+    });
+    criterion.bench_function("waters ciphertext from bytes", move |b| {
+        b.iter(|| CipherText::from_bytes(black_box(&c_bytes)))
+    });
 }
 
 fn criterion_waters_naccache_benchmark(criterion: &mut Criterion) {
     use ibe::waters_naccache::*;
 
-    let mut rng = rand::thread_rng();
+    let mut rng = seeded_rng();
 
     let id = "email:w.geraedts@sarif.nl".as_bytes();
     let kid = Identity::derive(id);
@@ -53,22 +144,22 @@ fn criterion_waters_naccache_benchmark(criterion: &mut Criterion) {
     let c = encrypt(&pk, &kid, &m, &mut rng);
 
     criterion.bench_function("waters_naccache generate message", |b| {
-        let mut rng = rand::thread_rng();
+        let mut rng = seeded_rng();
         b.iter(|| Message::generate(&mut rng))
     });
     criterion.bench_function("waters_naccache setup", |b| {
-        let mut rng = rand::thread_rng();
+        let mut rng = seeded_rng();
         b.iter(|| setup(&mut rng))
     });
     criterion.bench_function("waters_naccache derive", move |b| {
         b.iter(|| Identity::derive(id))
     });
     criterion.bench_function("waters_naccache extract", move |b| {
-        let mut rng = rand::thread_rng();
+        let mut rng = seeded_rng();
         b.iter(|| extract_usk(black_box(&pk), black_box(&sk), black_box(&kid), &mut rng))
     });
     criterion.bench_function("waters_naccache encrypt", move |b| {
-        let mut rng = rand::thread_rng();
+        let mut rng = seeded_rng();
         b.iter(|| encrypt(black_box(&pk), black_box(&kid), black_box(&m), &mut rng))
     });
     criterion.bench_function("waters_naccache decrypt", move |b| {
@@ -79,7 +170,7 @@ fn criterion_waters_naccache_benchmark(criterion: &mut Criterion) {
 fn criterion_kiltz_vahlis_one_benchmark(criterion: &mut Criterion) {
     use ibe::kiltz_vahlis_one::*;
 
-    let mut rng = rand::thread_rng();
+    let mut rng = seeded_rng();
 
     let id = "email:w.geraedts@sarif.nl".as_bytes();
     let kid = Identity::derive(id);
@@ -90,18 +181,18 @@ fn criterion_kiltz_vahlis_one_benchmark(criterion: &mut Criterion) {
     let (c, _k) = encrypt(&pk, &kid, &mut rng);
 
     criterion.bench_function("kiltz_vahlis_one setup", |b| {
-        let mut rng = rand::thread_rng();
+        let mut rng = seeded_rng();
         b.iter(|| setup(&mut rng))
     });
     criterion.bench_function("kiltz_vahlis_one derive", move |b| {
         b.iter(|| Identity::derive(id))
     });
     criterion.bench_function("kiltz_vahlis_one extract", move |b| {
-        let mut rng = rand::thread_rng();
+        let mut rng = seeded_rng();
         b.iter(|| extract_usk(black_box(&pk), black_box(&sk), black_box(&kid), &mut rng))
     });
     criterion.bench_function("kiltz_vahlis_one encrypt", move |b| {
-        let mut rng = rand::thread_rng();
+        let mut rng = seeded_rng();
         b.iter(|| encrypt(black_box(&pk), black_box(&kid), &mut rng))
     });
     criterion.bench_function("kiltz_vahlis_one decrypt", move |b| {
@@ -109,10 +200,220 @@ fn criterion_kiltz_vahlis_one_benchmark(criterion: &mut Criterion) {
     });
 }
 
+fn criterion_boneh_franklin_benchmark(criterion: &mut Criterion) {
+    use ibe::boneh_franklin::*;
+
+    let mut rng = seeded_rng();
+
+    let id = "email:w.geraedts@sarif.nl".as_bytes();
+    let kid = Identity::derive(id);
+
+    let m = Message::generate(&mut rng);
+
+    let (pk, sk) = setup(&mut rng);
+    let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+    let c = encrypt(&pk, &kid, &m, &mut rng);
+
+    criterion.bench_function("boneh_franklin setup", |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| setup(&mut rng))
+    });
+    criterion.bench_function("boneh_franklin derive", move |b| {
+        b.iter(|| Identity::derive(id))
+    });
+    criterion.bench_function("boneh_franklin extract", move |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| extract_usk(black_box(&pk), black_box(&sk), black_box(&kid), &mut rng))
+    });
+    criterion.bench_function("boneh_franklin encrypt", move |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| encrypt(black_box(&pk), black_box(&kid), black_box(&m), &mut rng))
+    });
+    criterion.bench_function("boneh_franklin decrypt", move |b| {
+        b.iter(|| decrypt(black_box(&usk), black_box(&c)))
+    });
+}
+
+fn criterion_boneh_boyen_one_benchmark(criterion: &mut Criterion) {
+    use ibe::boneh_boyen_one::*;
+
+    let mut rng = seeded_rng();
+
+    let id = "email:w.geraedts@sarif.nl".as_bytes();
+    let kid = Identity::derive(id);
+
+    let m = Message::generate(&mut rng);
+
+    let (pk, sk) = setup(&mut rng);
+    let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+    let c = encrypt(&pk, &kid, &m, &mut rng);
+
+    criterion.bench_function("boneh_boyen_one setup", |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| setup(&mut rng))
+    });
+    criterion.bench_function("boneh_boyen_one derive", move |b| {
+        b.iter(|| Identity::derive(id))
+    });
+    criterion.bench_function("boneh_boyen_one extract", move |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| extract_usk(black_box(&pk), black_box(&sk), black_box(&kid), &mut rng))
+    });
+    criterion.bench_function("boneh_boyen_one encrypt", move |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| encrypt(black_box(&pk), black_box(&kid), black_box(&m), &mut rng))
+    });
+    criterion.bench_function("boneh_boyen_one decrypt", move |b| {
+        b.iter(|| decrypt(black_box(&usk), black_box(&c)))
+    });
+}
+
+fn criterion_boyen_waters_benchmark(criterion: &mut Criterion) {
+    use ibe::boyen_waters::*;
+
+    let mut rng = seeded_rng();
+
+    let id = "email:w.geraedts@sarif.nl".as_bytes();
+    let kid = Identity::derive(id);
+
+    let m = Message::generate(&mut rng);
+
+    let (pk, sk) = setup(&mut rng);
+    let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+    let c = encrypt(&pk, &kid, &m, &mut rng);
+
+    criterion.bench_function("boyen_waters setup", |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| setup(&mut rng))
+    });
+    criterion.bench_function("boyen_waters derive", move |b| {
+        b.iter(|| Identity::derive(id))
+    });
+    criterion.bench_function("boyen_waters extract", move |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| extract_usk(black_box(&pk), black_box(&sk), black_box(&kid), &mut rng))
+    });
+    criterion.bench_function("boyen_waters encrypt", move |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| encrypt(black_box(&pk), black_box(&kid), black_box(&m), &mut rng))
+    });
+    criterion.bench_function("boyen_waters decrypt", move |b| {
+        b.iter(|| decrypt(black_box(&usk), black_box(&c)))
+    });
+}
+
+fn criterion_gentry_benchmark(criterion: &mut Criterion) {
+    use ibe::gentry::*;
+
+    let mut rng = seeded_rng();
+
+    let id = "email:w.geraedts@sarif.nl".as_bytes();
+    let kid = Identity::derive(id);
+
+    let m = Message::generate(&mut rng);
+
+    let (pk, sk) = setup(&mut rng);
+    let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+    let c = encrypt(&pk, &kid, &m, &mut rng);
+
+    criterion.bench_function("gentry setup", |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| setup(&mut rng))
+    });
+    criterion.bench_function("gentry derive", move |b| b.iter(|| Identity::derive(id)));
+    criterion.bench_function("gentry extract", move |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| extract_usk(black_box(&pk), black_box(&sk), black_box(&kid), &mut rng))
+    });
+    criterion.bench_function("gentry encrypt", move |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| encrypt(black_box(&pk), black_box(&kid), black_box(&m), &mut rng))
+    });
+    criterion.bench_function("gentry decrypt", move |b| {
+        b.iter(|| decrypt(black_box(&usk), black_box(&c)))
+    });
+}
+
+fn criterion_lewko_waters_benchmark(criterion: &mut Criterion) {
+    use ibe::lewko_waters::*;
+
+    let mut rng = seeded_rng();
+
+    let id = "email:w.geraedts@sarif.nl".as_bytes();
+    let kid = Identity::derive(id);
+
+    let m = Message::generate(&mut rng);
+
+    let (pk, sk) = setup(&mut rng);
+    let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+    let c = encrypt(&pk, &kid, &m, &mut rng);
+
+    criterion.bench_function("lewko_waters setup", |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| setup(&mut rng))
+    });
+    criterion.bench_function("lewko_waters derive", move |b| {
+        b.iter(|| Identity::derive(id))
+    });
+    criterion.bench_function("lewko_waters extract", move |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| extract_usk(black_box(&pk), black_box(&sk), black_box(&kid), &mut rng))
+    });
+    criterion.bench_function("lewko_waters encrypt", move |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| encrypt(black_box(&pk), black_box(&kid), black_box(&m), &mut rng))
+    });
+    criterion.bench_function("lewko_waters decrypt", move |b| {
+        b.iter(|| decrypt(black_box(&usk), black_box(&c)))
+    });
+}
+
+#[cfg(feature = "parallel")]
+fn criterion_waters_parallel_benchmark(criterion: &mut Criterion) {
+    use ibe::waters::*;
+
+    let mut rng = seeded_rng();
+
+    let ids: Vec<Identity> = (0..64)
+        .map(|i| Identity::derive_str(&format!("email:user{}@sarif.nl", i)))
+        .collect();
+    let m = Message::generate(&mut rng);
+    let (pk, _sk) = setup(&mut rng);
+    let ids2 = ids.clone();
+
+    criterion.bench_function("waters encrypt 64 serial", move |b| {
+        let mut rng = seeded_rng();
+        let ppk = pk.prepare();
+        b.iter(|| {
+            ids.iter()
+                .map(|v| encrypt_prepared(black_box(&ppk), v, black_box(&m), &mut rng))
+                .collect::<Vec<_>>()
+        })
+    });
+    criterion.bench_function("waters encrypt 64 parallel", move |b| {
+        let mut rng = seeded_rng();
+        b.iter(|| encrypt_batch_par(black_box(&pk), &ids2, black_box(&m), &mut rng))
+    });
+}
+
+#[cfg(not(feature = "parallel"))]
+fn criterion_waters_parallel_benchmark(_criterion: &mut Criterion) {}
+
 criterion_group!(
     benches,
     criterion_waters_benchmark,
+    criterion_waters_parallel_benchmark,
     criterion_waters_naccache_benchmark,
     criterion_kiltz_vahlis_one_benchmark,
+    criterion_boneh_franklin_benchmark,
+    criterion_boneh_boyen_one_benchmark,
+    criterion_boyen_waters_benchmark,
+    criterion_gentry_benchmark,
+    criterion_lewko_waters_benchmark,
 );
 criterion_main!(benches);
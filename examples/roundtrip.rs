@@ -0,0 +1,45 @@
+//! End-to-end usage of the Waters scheme.
+//!
+//! Run with `cargo run --example roundtrip -- <identity>`.
+
+use ibe::error::ParseError;
+use ibe::waters::*;
+
+fn main() {
+    let id = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "email:w.geraedts@sarif.nl".to_string());
+
+    if let Err(e) = run(&id) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(id: &str) -> Result<(), ParseError> {
+    let mut rng = rand::thread_rng();
+
+    // Generate a public-private-keypair for the PKG.
+    let (pk, sk) = setup(&mut rng);
+    println!("public key: {}", pk);
+
+    // Hash the identity and extract a user secret key for it.
+    let kid = Identity::derive_str(id);
+    let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+    println!("user secret key for {:?}: {}", id, usk);
+
+    // Encrypt a random message for the identity.
+    let m = Message::generate(&mut rng);
+    let c = encrypt(&pk, &kid, &m, &mut rng);
+    println!("ciphertext: {}", c);
+
+    // The artifacts are transferred as text, and parsed again by the receiver.
+    let usk: UserSecretKey = usk.to_string().parse()?;
+    let c: CipherText = c.to_string().parse()?;
+
+    let m2 = decrypt(&usk, &c);
+    assert_eq!(m, m2);
+    println!("decrypted key: {:02x?}", m2.into_aes_key());
+
+    Ok(())
+}
@@ -0,0 +1,17 @@
+#![no_main]
+
+use core::convert::TryFrom;
+use ibe::waters::{CipherText, CIPHERTEXT_BYTES};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(bytes) = <&[u8; CIPHERTEXT_BYTES]>::try_from(data) {
+        let _ = CipherText::from_bytes(bytes);
+        let _ = CipherText::try_from_bytes(bytes);
+    }
+    let _ = CipherText::try_from(data);
+    let _ = CipherText::from_versioned_bytes(data);
+    if let Ok(s) = core::str::from_utf8(data) {
+        let _ = s.parse::<CipherText>();
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use core::convert::TryFrom;
+use ibe::waters::{Message, MESSAGE_BYTES};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(bytes) = <&[u8; MESSAGE_BYTES]>::try_from(data) {
+        let _ = Message::from_bytes(bytes);
+        let _ = Message::try_from_bytes(bytes);
+    }
+    let _ = Message::try_from(data);
+});
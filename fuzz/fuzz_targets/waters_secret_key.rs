@@ -0,0 +1,13 @@
+#![no_main]
+
+use core::convert::TryFrom;
+use ibe::waters::{SecretKey, SECRET_KEY_BYTES};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(bytes) = <&[u8; SECRET_KEY_BYTES]>::try_from(data) {
+        let _ = SecretKey::from_bytes(bytes);
+        let _ = SecretKey::try_from_bytes(bytes);
+    }
+    let _ = SecretKey::try_from(data);
+});
@@ -0,0 +1,16 @@
+#![no_main]
+
+use core::convert::TryFrom;
+use ibe::waters::{UserSecretKey, USER_SECRET_KEY_BYTES};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(bytes) = <&[u8; USER_SECRET_KEY_BYTES]>::try_from(data) {
+        let _ = UserSecretKey::from_bytes(bytes);
+        let _ = UserSecretKey::try_from_bytes(bytes);
+    }
+    let _ = UserSecretKey::try_from(data);
+    if let Ok(s) = core::str::from_utf8(data) {
+        let _ = s.parse::<UserSecretKey>();
+    }
+});
@@ -0,0 +1,351 @@
+//! Identity Based Encryption Boneh-Boyen BB1 scheme on the [BLS12-381 pairing-friendly elliptic curve](https://github.com/zkcrypto/bls12_381).
+//!  * From: "[Efficient Selective-ID Secure Identity-Based Encryption Without Random Oracles](https://link.springer.com/chapter/10.1007/978-3-540-24676-3_14)"
+//!  * Published in: EUROCRYPT, 2004
+//!  * Adapted to asymmetric pairings as in IEEE P1363.3
+//!
+//! Uses [SHA3-512](https://crates.io/crates/tiny-keccak) for hashing to identities.
+//!
+//! Unlike Waters, the public key has a constant size, and extraction requires no
+//! entanglement of the identity with the public key.
+//! Only provides selective-identity security in the standard model.
+//!
+//! The structure of the byte serialisation of the various datastructures is not guaranteed
+//! to remain constant between releases of this library.
+//! All operations in this library are implemented to run in constant time.
+
+use arrayref::{array_refs, mut_array_refs};
+use rand::{CryptoRng, RngCore};
+use subtle::CtOption;
+
+use crate::util::*;
+use irmaseal_curve::{G1Affine, G2Affine, Gt, Scalar};
+
+const PUBLICKEYSIZE: usize = 3 * 48 + 96 + 288;
+
+/// Public key parameters generated by the PKG used to encrypt messages.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PublicKey {
+    g: G1Affine,
+    g1: G1Affine,
+    g3: G1Affine,
+    ghat: G2Affine,
+    v: Gt,
+}
+
+/// Secret key parameters generated by the PKG used to extract user secret keys.
+/// The `Debug` output is redacted.
+#[derive(Clone, PartialEq)]
+pub struct SecretKey {
+    alpha: Scalar,
+    beta: Scalar,
+    gamma: Scalar,
+}
+
+/// Points on the paired curves that form the user secret key.
+/// The `Debug` output is redacted.
+#[derive(Clone, PartialEq)]
+pub struct UserSecretKey {
+    k0: G2Affine,
+    k1: G2Affine,
+}
+
+/// Field element for an identity.
+///
+/// Effectively a hash of an identity, mapped to the curve field.
+/// Together with the public key parameters generated by the PKG forms the user public key.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Identity(Scalar);
+
+/// A point on the paired curve that can be encrypted and decrypted.
+///
+/// You can use the byte representation to derive an AES key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Message(Gt);
+
+/// Encrypted message. Can only be decrypted with an user secret key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CipherText {
+    c0: Gt,
+    c1: G1Affine,
+    c2: G1Affine,
+}
+
+/// The Boneh-Boyen BB1 scheme, implementing the common `IbeScheme` interface.
+#[derive(Clone, Copy, Debug)]
+pub struct BonehBoyenOne;
+
+impl_ibe_scheme!(BonehBoyenOne);
+
+/// Generate a keypair used by the Private Key Generator (PKG).
+pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+    let g: G1Affine = rand_g1(rng).into();
+    let ghat: G2Affine = rand_g2(rng).into();
+
+    let alpha = rand_scalar(rng);
+    let beta = rand_scalar(rng);
+    let gamma = rand_scalar(rng);
+
+    let g1 = (g * alpha).into();
+    let g3 = (g * gamma).into();
+    let v = irmaseal_curve::pairing(&g, &ghat) * (alpha * beta);
+
+    let pk = PublicKey { g, g1, g3, ghat, v };
+    let sk = SecretKey { alpha, beta, gamma };
+
+    (pk, sk)
+}
+
+/// Extract an user secret key for a given identity.
+pub fn extract_usk<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    sk: &SecretKey,
+    v: &Identity,
+    rng: &mut R,
+) -> UserSecretKey {
+    let r = rand_scalar(rng);
+
+    let k0 = (pk.ghat * (sk.alpha * sk.beta + r * (sk.alpha * v.0 + sk.gamma))).into();
+    let k1 = (pk.ghat * r).into();
+
+    UserSecretKey { k0, k1 }
+}
+
+/// Encrypt a message using the PKG public key and an identity.
+pub fn encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let s = rand_scalar(rng);
+
+    let c0 = pk.v * s + m.0;
+    let c1 = (pk.g * s).into();
+    let c2 = ((pk.g1 * v.0 + pk.g3) * s).into();
+
+    CipherText { c0, c1, c2 }
+}
+
+/// Decrypt ciphertext to a message using a user secret key.
+pub fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
+    let num = irmaseal_curve::pairing(&c.c2, &usk.k1);
+    let dem = irmaseal_curve::pairing(&c.c1, &usk.k0);
+
+    let m = c.c0 + num - dem;
+    Message(m)
+}
+
+impl Identity {
+    /// Hash a byte slice to an Identity field element, which acts as a user public key.
+    /// Uses sha3-512 internally.
+    pub fn derive(b: &[u8]) -> Identity {
+        Identity(Scalar::from_bytes_wide(&tiny_keccak::sha3_512(b)))
+    }
+
+    /// Hash a string slice to an Identity field element.
+    /// Directly converts characters to UTF-8 byte representation.
+    pub fn derive_str(s: &str) -> Identity {
+        Self::derive(s.as_bytes())
+    }
+}
+
+impl PublicKey {
+    pub fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
+        let mut res = [0u8; PUBLICKEYSIZE];
+        let (g, g1, g3, ghat, v) = mut_array_refs![&mut res, 48, 48, 48, 96, 288];
+        *g = self.g.to_compressed();
+        *g1 = self.g1.to_compressed();
+        *g3 = self.g3.to_compressed();
+        *ghat = self.ghat.to_compressed();
+        *v = self.v.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; PUBLICKEYSIZE]) -> CtOption<Self> {
+        let (g, g1, g3, ghat, v) = array_refs![bytes, 48, 48, 48, 96, 288];
+
+        let g = G1Affine::from_compressed(g);
+        let g1 = G1Affine::from_compressed(g1);
+        let g3 = G1Affine::from_compressed(g3);
+        let ghat = G2Affine::from_compressed(ghat);
+        let v = Gt::from_compressed(v);
+
+        g.and_then(|g| {
+            g1.and_then(|g1| {
+                g3.and_then(|g3| ghat.and_then(|ghat| v.map(|v| PublicKey { g, g1, g3, ghat, v })))
+            })
+        })
+    }
+}
+
+impl SecretKey {
+    pub fn to_bytes(&self) -> [u8; 96] {
+        let mut res = [0u8; 96];
+        let (alpha, beta, gamma) = mut_array_refs![&mut res, 32, 32, 32];
+        *alpha = self.alpha.to_bytes();
+        *beta = self.beta.to_bytes();
+        *gamma = self.gamma.to_bytes();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 96]) -> CtOption<Self> {
+        let (alpha, beta, gamma) = array_refs![bytes, 32, 32, 32];
+
+        let alpha = Scalar::from_bytes(alpha);
+        let beta = Scalar::from_bytes(beta);
+        let gamma = Scalar::from_bytes(gamma);
+
+        alpha.and_then(|alpha| {
+            beta.and_then(|beta| gamma.map(|gamma| SecretKey { alpha, beta, gamma }))
+        })
+    }
+}
+
+impl UserSecretKey {
+    pub fn to_bytes(&self) -> [u8; 192] {
+        let mut res = [0u8; 192];
+        let (k0, k1) = mut_array_refs![&mut res, 96, 96];
+        *k0 = self.k0.to_compressed();
+        *k1 = self.k1.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 192]) -> CtOption<Self> {
+        let (k0, k1) = array_refs![bytes, 96, 96];
+
+        let k0 = G2Affine::from_compressed(k0);
+        let k1 = G2Affine::from_compressed(k1);
+
+        k0.and_then(|k0| k1.map(|k1| UserSecretKey { k0, k1 }))
+    }
+}
+
+impl Message {
+    /// Generate a random point on the paired curve.
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self(rand_gt(rng))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 288] {
+        self.0.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
+        Gt::from_compressed(bytes).map(Message)
+    }
+}
+
+impl CipherText {
+    pub fn to_bytes(&self) -> [u8; 384] {
+        let mut res = [0u8; 384];
+        let (c0, c1, c2) = mut_array_refs![&mut res, 288, 48, 48];
+        *c0 = self.c0.to_compressed();
+        *c1 = self.c1.to_compressed();
+        *c2 = self.c2.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 384]) -> CtOption<Self> {
+        let (c0, c1, c2) = array_refs![bytes, 288, 48, 48];
+
+        let c0 = Gt::from_compressed(c0);
+        let c1 = G1Affine::from_compressed(c1);
+        let c2 = G1Affine::from_compressed(c2);
+
+        c0.and_then(|c0| c1.and_then(|c1| c2.map(|c2| CipherText { c0, c1, c2 })))
+    }
+}
+
+impl core::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("SecretKey(<redacted>)")
+    }
+}
+
+impl core::fmt::Debug for UserSecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("UserSecretKey(<redacted>)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    #[allow(dead_code)]
+    struct DefaultSubResults {
+        kid: Identity,
+        m: Message,
+        pk: PublicKey,
+        sk: SecretKey,
+        usk: UserSecretKey,
+        c: CipherText,
+    }
+
+    fn perform_default() -> DefaultSubResults {
+        let mut rng = rand::thread_rng();
+
+        let id = ID.as_bytes();
+        let kid = Identity::derive(id);
+
+        let m = Message::generate(&mut rng);
+
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let c = encrypt(&pk, &kid, &m, &mut rng);
+
+        DefaultSubResults {
+            kid,
+            m,
+            pk,
+            sk,
+            usk,
+            c,
+        }
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt() {
+        let results = perform_default();
+        let m2 = decrypt(&results.usk, &results.c);
+
+        assert_eq!(results.m, m2);
+    }
+
+    #[test]
+    fn debug_output() {
+        let results = perform_default();
+
+        assert_eq!(std::format!("{:?}", results.sk), "SecretKey(<redacted>)");
+        assert_eq!(
+            std::format!("{:?}", results.usk),
+            "UserSecretKey(<redacted>)"
+        );
+    }
+
+    #[test]
+    fn eq_serialize_deserialize() {
+        let result = perform_default();
+
+        assert_eq!(result.m, Message::from_bytes(&result.m.to_bytes()).unwrap());
+        assert_eq!(
+            result.pk,
+            PublicKey::from_bytes(&result.pk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.sk,
+            SecretKey::from_bytes(&result.sk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.usk,
+            UserSecretKey::from_bytes(&result.usk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.c,
+            CipherText::from_bytes(&result.c.to_bytes()).unwrap()
+        );
+    }
+}
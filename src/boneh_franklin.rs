@@ -0,0 +1,431 @@
+//! Identity Based Encryption Boneh-Franklin FullIdent scheme on the [BLS12-381 pairing-friendly elliptic curve](https://github.com/zkcrypto/bls12_381).
+//!  * From: "[Identity-Based Encryption from the Weil Pairing](https://link.springer.com/chapter/10.1007/3-540-44647-8_13)"
+//!  * Published in: CRYPTO, 2001
+//!
+//! Uses [SHAKE256](https://crates.io/crates/tiny-keccak) for hashing identities to the curve,
+//! and domain separated [SHA3](https://crates.io/crates/tiny-keccak) for the other random oracles.
+//!
+//! Messages are 32 bytes long, making ciphertexts only 160 bytes.
+//! Decryption verifies the integrity of the ciphertext (Fujisaki-Okamoto transform),
+//! so unlike the other schemes `decrypt` returns `None` for an invalid ciphertext.
+//! The `IbeScheme` implementation uses `decrypt_implicit` instead, which returns a
+//! pseudorandom message, like decrypting with the wrong key does in the other schemes.
+//!
+//! The structure of the byte serialisation of the various datastructures is not guaranteed
+//! to remain constant between releases of this library.
+//! All operations in this library are implemented to run in constant time.
+
+use arrayref::{array_refs, mut_array_refs};
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+
+use crate::util::*;
+use irmaseal_curve::{G1Affine, G2Affine, Gt, Scalar};
+
+const MESSAGE_BYTE_LEN: usize = 32;
+const SIGMA_BYTE_LEN: usize = 32;
+
+const PUBLICKEYSIZE: usize = 96;
+const CIPHERTEXTSIZE: usize = 96 + SIGMA_BYTE_LEN + MESSAGE_BYTE_LEN;
+
+const H1_DST: &[u8] = b"ibe-boneh-franklin-h1";
+const H2_DST: &[u8] = b"ibe-boneh-franklin-h2";
+const H3_DST: &[u8] = b"ibe-boneh-franklin-h3";
+const H4_DST: &[u8] = b"ibe-boneh-franklin-h4";
+const REJECT_DST: &[u8] = b"ibe-boneh-franklin-reject";
+
+/// Public key parameter generated by the PKG used to encrypt messages.
+///
+/// The generator of G2 to the power of the secret key.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PublicKey {
+    gpub: G2Affine,
+}
+
+/// Secret key parameter generated by the PKG used to extract user secret keys.
+///
+/// With the `zeroize` feature enabled, the key material is wiped on drop.
+/// The `Debug` output is redacted.
+#[derive(Clone, PartialEq)]
+pub struct SecretKey {
+    s: Scalar,
+}
+
+/// Point on the curve that forms the user secret key.
+///
+/// With the `zeroize` feature enabled, the key material is wiped on drop.
+/// The `Debug` output is redacted.
+#[derive(Clone, PartialEq)]
+pub struct UserSecretKey {
+    d: G1Affine,
+}
+
+/// An identity hashed to a point on the curve.
+///
+/// Together with the public key parameters generated by the PKG forms the user public key.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Identity(G1Affine);
+
+/// A 32-byte message that can be encrypted and decrypted.
+///
+/// Can directly be used as a symmetric key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Message([u8; MESSAGE_BYTE_LEN]);
+
+/// Encrypted message. Can only be decrypted with an user secret key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CipherText {
+    u: G2Affine,
+    v: [u8; SIGMA_BYTE_LEN],
+    w: [u8; MESSAGE_BYTE_LEN],
+}
+
+/// The Boneh-Franklin scheme, implementing the common `IbeScheme` interface.
+///
+/// Decrypts using `decrypt_implicit`, as the interface does not allow decryption to fail.
+#[derive(Clone, Copy, Debug)]
+pub struct BonehFranklin;
+
+impl_ibe_scheme!(BonehFranklin, decrypt_implicit);
+
+/// Generate a keypair used by the Private Key Generator (PKG).
+pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+    let s = rand_secret_scalar(rng);
+    let gpub = (G2Affine::generator() * *s).into();
+
+    (PublicKey { gpub }, SecretKey { s: *s })
+}
+
+/// Extract an user secret key for a given identity.
+///
+/// Takes the public key and a random number generator to match the other schemes,
+/// but needs neither, as the user secret key is determined by the identity.
+pub fn extract_usk<R: RngCore + CryptoRng>(
+    _pk: &PublicKey,
+    sk: &SecretKey,
+    v: &Identity,
+    _rng: &mut R,
+) -> UserSecretKey {
+    let d = (v.0 * sk.s).into();
+
+    UserSecretKey { d }
+}
+
+/// Mask the random value sigma using the pairing value shared between sender and receiver.
+fn h2(gid: &Gt) -> [u8; SIGMA_BYTE_LEN] {
+    sha3_256_dst(H2_DST, &[&gid.to_compressed()])
+}
+
+/// Derive the encryption randomness from sigma and the message.
+fn h3(sigma: &[u8; SIGMA_BYTE_LEN], m: &Message) -> Scalar {
+    Scalar::from_bytes_wide(&sha3_512_dst(H3_DST, &[sigma, &m.0]))
+}
+
+/// Mask the message using the random value sigma.
+fn h4(sigma: &[u8; SIGMA_BYTE_LEN]) -> [u8; MESSAGE_BYTE_LEN] {
+    sha3_256_dst(H4_DST, &[sigma])
+}
+
+fn xor<const N: usize>(a: &[u8; N], b: &[u8; N]) -> [u8; N] {
+    let mut res = [0u8; N];
+    for (r, (x, y)) in res.iter_mut().zip(a.iter().zip(b.iter())) {
+        *r = x ^ y;
+    }
+    res
+}
+
+/// Encrypt a message using the PKG public key and an identity.
+pub fn encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let mut sigma = [0u8; SIGMA_BYTE_LEN];
+    rng.fill_bytes(&mut sigma);
+
+    let r = h3(&sigma, m);
+
+    let u = (G2Affine::generator() * r).into();
+    let gid = irmaseal_curve::pairing(&(v.0 * r).into(), &pk.gpub);
+    let v = xor(&sigma, &h2(&gid));
+    let w = xor(&m.0, &h4(&sigma));
+
+    CipherText { u, v, w }
+}
+
+/// Decrypt ciphertext to a message using a user secret key.
+///
+/// Returns `None` if the ciphertext has been tampered with,
+/// or was not encrypted for the identity of the user secret key.
+pub fn decrypt(usk: &UserSecretKey, c: &CipherText) -> CtOption<Message> {
+    let gid = irmaseal_curve::pairing(&usk.d, &c.u);
+    let sigma = xor(&c.v, &h2(&gid));
+    let m = Message(xor(&c.w, &h4(&sigma)));
+
+    let r = h3(&sigma, &m);
+    let u: G2Affine = (G2Affine::generator() * r).into();
+
+    CtOption::new(m, u.ct_eq(&c.u))
+}
+
+/// Decrypt ciphertext to a message using a user secret key, without reporting failure.
+///
+/// Returns the same message as `decrypt` for a valid ciphertext. Otherwise, returns a pseudorandom
+/// message derived from the user secret key and the ciphertext (implicit rejection), which can not
+/// be told apart from a valid message without knowing the user secret key.
+pub fn decrypt_implicit(usk: &UserSecretKey, c: &CipherText) -> Message {
+    let reject = Message(sha3_256_dst(
+        REJECT_DST,
+        &[&usk.d.to_compressed(), &c.to_bytes()],
+    ));
+
+    decrypt(usk, c).unwrap_or(reject)
+}
+
+impl Identity {
+    /// Hash a byte slice to a point on the curve, which acts as a user public key.
+    /// Uses SHAKE256 internally.
+    pub fn derive(b: &[u8]) -> Identity {
+        Identity(hash_to_g1(b, H1_DST).into())
+    }
+
+    /// Hash a string slice to a point on the curve.
+    /// Directly converts characters to UTF-8 byte representation.
+    pub fn derive_str(s: &str) -> Identity {
+        Self::derive(s.as_bytes())
+    }
+}
+
+impl PublicKey {
+    pub fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
+        let mut res = [0u8; PUBLICKEYSIZE];
+        res.copy_from_slice(&self.gpub.to_compressed());
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; PUBLICKEYSIZE]) -> CtOption<Self> {
+        G2Affine::from_compressed(bytes).map(|gpub| PublicKey { gpub })
+    }
+}
+
+impl SecretKey {
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.s.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 32]) -> CtOption<Self> {
+        Scalar::from_bytes(bytes).map(|s| SecretKey { s })
+    }
+}
+
+impl UserSecretKey {
+    pub fn to_bytes(&self) -> [u8; 48] {
+        self.d.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 48]) -> CtOption<Self> {
+        G1Affine::from_compressed(bytes).map(|d| UserSecretKey { d })
+    }
+}
+
+impl Message {
+    /// Generate a random message.
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut m = [0u8; MESSAGE_BYTE_LEN];
+        rng.fill_bytes(&mut m);
+        Message(m)
+    }
+
+    pub fn to_bytes(&self) -> [u8; MESSAGE_BYTE_LEN] {
+        self.0
+    }
+
+    pub fn from_bytes(bytes: &[u8; MESSAGE_BYTE_LEN]) -> Self {
+        Message(*bytes)
+    }
+}
+
+impl CipherText {
+    pub fn to_bytes(&self) -> [u8; CIPHERTEXTSIZE] {
+        let mut res = [0u8; CIPHERTEXTSIZE];
+        let (u, v, w) = mut_array_refs![&mut res, 96, SIGMA_BYTE_LEN, MESSAGE_BYTE_LEN];
+        *u = self.u.to_compressed();
+        *v = self.v;
+        *w = self.w;
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; CIPHERTEXTSIZE]) -> CtOption<Self> {
+        let (u, v, w) = array_refs![bytes, 96, SIGMA_BYTE_LEN, MESSAGE_BYTE_LEN];
+
+        G2Affine::from_compressed(u).map(|u| CipherText { u, v: *v, w: *w })
+    }
+}
+
+impl ConditionallySelectable for Message {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut res = [0u8; MESSAGE_BYTE_LEN];
+        for (r, (x, y)) in res.iter_mut().zip(a.0.iter().zip(b.0.iter())) {
+            *r = u8::conditional_select(x, y, choice);
+        }
+        Message(res)
+    }
+}
+
+impl Default for Message {
+    fn default() -> Self {
+        Message([0u8; MESSAGE_BYTE_LEN])
+    }
+}
+
+impl core::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("SecretKey(<redacted>)")
+    }
+}
+
+impl core::fmt::Debug for UserSecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("UserSecretKey(<redacted>)")
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for SecretKey {
+    fn zeroize(&mut self) {
+        self.s.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for SecretKey {}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for UserSecretKey {
+    fn zeroize(&mut self) {
+        self.d.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for UserSecretKey {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for UserSecretKey {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    #[allow(dead_code)]
+    struct DefaultSubResults {
+        kid: Identity,
+        m: Message,
+        pk: PublicKey,
+        sk: SecretKey,
+        usk: UserSecretKey,
+        c: CipherText,
+    }
+
+    fn perform_default() -> DefaultSubResults {
+        let mut rng = rand::thread_rng();
+
+        let id = ID.as_bytes();
+        let kid = Identity::derive(id);
+
+        let m = Message::generate(&mut rng);
+
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let c = encrypt(&pk, &kid, &m, &mut rng);
+
+        DefaultSubResults {
+            kid,
+            m,
+            pk,
+            sk,
+            usk,
+            c,
+        }
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt() {
+        let results = perform_default();
+        let m2 = decrypt(&results.usk, &results.c);
+
+        assert_eq!(results.m, m2.unwrap());
+        assert_eq!(results.m, decrypt_implicit(&results.usk, &results.c));
+    }
+
+    #[test]
+    fn decrypt_rejects_tampering() {
+        let results = perform_default();
+
+        let mut c = results.c;
+        c.w[0] ^= 1;
+        assert!(bool::from(decrypt(&results.usk, &c).is_none()));
+
+        let reject = decrypt_implicit(&results.usk, &c);
+        assert_ne!(reject, results.m);
+        assert_eq!(reject, decrypt_implicit(&results.usk, &c));
+
+        let other = extract_usk(
+            &results.pk,
+            &results.sk,
+            &Identity::derive_str("email:someone@else.nl"),
+            &mut rand::thread_rng(),
+        );
+        assert!(bool::from(decrypt(&other, &results.c).is_none()));
+        assert_ne!(decrypt_implicit(&other, &results.c), reject);
+    }
+
+    #[test]
+    fn debug_output() {
+        let results = perform_default();
+
+        assert_eq!(std::format!("{:?}", results.sk), "SecretKey(<redacted>)");
+        assert_eq!(
+            std::format!("{:?}", results.usk),
+            "UserSecretKey(<redacted>)"
+        );
+    }
+
+    #[test]
+    fn eq_serialize_deserialize() {
+        let result = perform_default();
+
+        assert_eq!(result.m, Message::from_bytes(&result.m.to_bytes()));
+        assert_eq!(
+            result.pk,
+            PublicKey::from_bytes(&result.pk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.sk,
+            SecretKey::from_bytes(&result.sk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.usk,
+            UserSecretKey::from_bytes(&result.usk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.c,
+            CipherText::from_bytes(&result.c.to_bytes()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,624 @@
+//! Anonymous Identity Based Encryption Boyen-Waters scheme on the [BLS12-381 pairing-friendly elliptic curve](https://github.com/zkcrypto/bls12_381).
+//!  * From: "[Anonymous Hierarchical Identity-Based Encryption (Without Random Oracles)](https://link.springer.com/chapter/10.1007/11818175_17)"
+//!  * Published in: CRYPTO, 2006
+//!  * Adapted to asymmetric pairings as in "[Anonymity from Asymmetry](https://link.springer.com/chapter/10.1007/978-3-642-13013-7_9)", PKC 2010
+//!
+//! Uses [SHA3-512](https://crates.io/crates/tiny-keccak) for hashing to identities.
+//!
+//! Unlike the other schemes, ciphertexts hide the identity they were encrypted for.
+//! In for example the Waters scheme, anyone with the public key can test whether a ciphertext
+//! was encrypted for a guessed identity, by checking `e(c3, g) = e(entangle(v), c2)`.
+//! Here, all ciphertext elements live in G1, and the public key contains no elements of G2
+//! that could be used for such a test. The only way to learn the recipient of a ciphertext
+//! is by trial decryption with the user secret keys of candidate identities.
+//! As `decrypt` with the wrong key results in a random message that looks like any other,
+//! trial decryption requires the key confirmation tag added by `encapsulate`,
+//! or redundancy in the message added by the caller.
+//! The number of ciphertexts and their sizes are of course not hidden.
+//! Only provides selective-identity security in the standard model.
+//!
+//! The structure of the byte serialisation of the various datastructures is not guaranteed
+//! to remain constant between releases of this library.
+//! All operations in this library are implemented to run in constant time.
+
+use arrayref::{array_refs, mut_array_refs};
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+
+use crate::util::*;
+use irmaseal_curve::{G1Affine, G2Affine, Gt, Scalar};
+
+const PUBLICKEYSIZE: usize = 7 * 48 + 288;
+const SECRETKEYSIZE: usize = 96 + 7 * 32;
+const USERSECRETKEYSIZE: usize = 5 * 96;
+const CIPHERTEXTSIZE: usize = 288 + 5 * 48;
+const KEMCIPHERTEXTSIZE: usize = CIPHERTEXTSIZE + 32;
+
+const KEM_KEY_DST: &[u8] = b"ibe-boyen-waters-kem-key";
+const KEM_TAG_DST: &[u8] = b"ibe-boyen-waters-kem-tag";
+
+/// Public key parameters generated by the PKG used to encrypt messages.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PublicKey {
+    g: G1Affine,
+    g0: G1Affine,
+    g1: G1Affine,
+    v1: G1Affine,
+    v2: G1Affine,
+    v3: G1Affine,
+    v4: G1Affine,
+    omega: Gt,
+}
+
+/// Secret key parameters generated by the PKG used to extract user secret keys.
+///
+/// Contains the generator of G2 and the discrete logarithms of `g0` and `g1`,
+/// as these may not be published without losing anonymity.
+/// The `Debug` output is redacted.
+#[derive(Clone, PartialEq)]
+pub struct SecretKey {
+    ghat: G2Affine,
+    y0: Scalar,
+    y1: Scalar,
+    w: Scalar,
+    t1: Scalar,
+    t2: Scalar,
+    t3: Scalar,
+    t4: Scalar,
+}
+
+/// Points on the paired curves that form the user secret key.
+/// The `Debug` output is redacted.
+#[derive(Clone, PartialEq)]
+pub struct UserSecretKey {
+    d0: G2Affine,
+    d1: G2Affine,
+    d2: G2Affine,
+    d3: G2Affine,
+    d4: G2Affine,
+}
+
+/// Field element for an identity.
+///
+/// Effectively a hash of an identity, mapped to the curve field.
+/// Together with the public key parameters generated by the PKG forms the user public key.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Identity(Scalar);
+
+/// A point on the paired curve that can be encrypted and decrypted.
+///
+/// You can use the byte representation to derive an AES key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Message(Gt);
+
+/// Encrypted message. Can only be decrypted with an user secret key.
+///
+/// Does not reveal the identity it was encrypted for.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct CipherText {
+    cprime: Gt,
+    c0: G1Affine,
+    c1: G1Affine,
+    c2: G1Affine,
+    c3: G1Affine,
+    c4: G1Affine,
+}
+
+/// Encapsulated shared secret, with a tag confirming whether a user secret key decapsulates it.
+///
+/// Created using `encapsulate`. Like `CipherText`, it does not reveal the identity it was
+/// encrypted for, as the tag is a hash of the encrypted random message.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KemCipherText {
+    c: CipherText,
+    tag: [u8; 32],
+}
+
+/// The anonymous Boyen-Waters scheme, implementing the common `IbeScheme` interface.
+#[derive(Clone, Copy, Debug)]
+pub struct BoyenWaters;
+
+impl_ibe_scheme!(BoyenWaters);
+
+/// Generate a keypair used by the Private Key Generator (PKG).
+pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+    let g: G1Affine = rand_g1(rng).into();
+    let ghat: G2Affine = rand_g2(rng).into();
+
+    let y0 = rand_scalar(rng);
+    let y1 = rand_scalar(rng);
+    let w = rand_scalar(rng);
+    let t1 = rand_scalar(rng);
+    let t2 = rand_scalar(rng);
+    let t3 = rand_scalar(rng);
+    let t4 = rand_scalar(rng);
+
+    let pk = PublicKey {
+        g,
+        g0: (g * y0).into(),
+        g1: (g * y1).into(),
+        v1: (g * t1).into(),
+        v2: (g * t2).into(),
+        v3: (g * t3).into(),
+        v4: (g * t4).into(),
+        omega: irmaseal_curve::pairing(&g, &ghat) * (t1 * t2 * w),
+    };
+
+    let sk = SecretKey {
+        ghat,
+        y0,
+        y1,
+        w,
+        t1,
+        t2,
+        t3,
+        t4,
+    };
+
+    (pk, sk)
+}
+
+/// Extract an user secret key for a given identity.
+pub fn extract_usk<R: RngCore + CryptoRng>(
+    _pk: &PublicKey,
+    sk: &SecretKey,
+    v: &Identity,
+    rng: &mut R,
+) -> UserSecretKey {
+    let r1 = rand_scalar(rng);
+    let r2 = rand_scalar(rng);
+
+    // The discrete logarithm of g0 * g1^id, such that it can be computed in G2.
+    let h = sk.y0 + sk.y1 * v.0;
+
+    let d0 = (sk.ghat * (r1 * sk.t1 * sk.t2 + r2 * sk.t3 * sk.t4)).into();
+    let d1 = (sk.ghat * -(sk.w * sk.t2 + h * r1 * sk.t2)).into();
+    let d2 = (sk.ghat * -(sk.w * sk.t1 + h * r1 * sk.t1)).into();
+    let d3 = (sk.ghat * -(h * r2 * sk.t4)).into();
+    let d4 = (sk.ghat * -(h * r2 * sk.t3)).into();
+
+    UserSecretKey { d0, d1, d2, d3, d4 }
+}
+
+/// Encrypt a message using the PKG public key and an identity.
+pub fn encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let s = rand_scalar(rng);
+    let s1 = rand_scalar(rng);
+    let s2 = rand_scalar(rng);
+
+    let cprime = pk.omega * s + m.0;
+    let c0 = ((pk.g0 + pk.g1 * v.0) * s).into();
+    let c1 = (pk.v1 * (s - s1)).into();
+    let c2 = (pk.v2 * s1).into();
+    let c3 = (pk.v3 * (s - s2)).into();
+    let c4 = (pk.v4 * s2).into();
+
+    CipherText {
+        cprime,
+        c0,
+        c1,
+        c2,
+        c3,
+        c4,
+    }
+}
+
+/// Decrypt ciphertext to a message using a user secret key.
+///
+/// Decrypting with the user secret key of another identity results in a random message,
+/// which can not be told apart from the right one. To find the recipient by trial decryption,
+/// use `encapsulate` and `decapsulate`, or add redundancy to the message before encrypting.
+pub fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
+    let k = pairing_product(&[
+        (c.c0, usk.d0),
+        (c.c1, usk.d1),
+        (c.c2, usk.d2),
+        (c.c3, usk.d3),
+        (c.c4, usk.d4),
+    ]);
+
+    Message(c.cprime + k)
+}
+
+/// Generate a random message, encrypt it and derive a 32-byte shared secret from it.
+///
+/// Adds a tag to the ciphertext, such that `decapsulate` can confirm whether it
+/// was given the user secret key for the identity the ciphertext was encrypted for.
+pub fn encapsulate<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    rng: &mut R,
+) -> (KemCipherText, [u8; 32]) {
+    let m = Message::generate(rng);
+    let c = encrypt(pk, v, &m, rng);
+    let m = m.to_bytes();
+
+    let tag = kem_tag(&m, &c);
+    (KemCipherText { c, tag }, sha3_256_dst(KEM_KEY_DST, &[&m]))
+}
+
+/// Decrypt a ciphertext produced by `encapsulate` to the same 32-byte shared secret.
+///
+/// Returns `None` if the user secret key is not for the identity the ciphertext was encrypted for,
+/// or the ciphertext was modified.
+pub fn decapsulate(usk: &UserSecretKey, c: &KemCipherText) -> CtOption<[u8; 32]> {
+    let m = decrypt(usk, &c.c).to_bytes();
+
+    let valid = kem_tag(&m, &c.c).ct_eq(&c.tag);
+    CtOption::new(sha3_256_dst(KEM_KEY_DST, &[&m]), valid)
+}
+
+/// Hash a message and its ciphertext to a key confirmation tag using a domain-separated sha3-256.
+fn kem_tag(m: &[u8; 288], c: &CipherText) -> [u8; 32] {
+    sha3_256_dst(KEM_TAG_DST, &[m, &c.to_bytes()])
+}
+
+impl Identity {
+    /// Hash a byte slice to an Identity field element, which acts as a user public key.
+    /// Uses sha3-512 internally.
+    pub fn derive(b: &[u8]) -> Identity {
+        Identity(Scalar::from_bytes_wide(&tiny_keccak::sha3_512(b)))
+    }
+
+    /// Hash a string slice to an Identity field element.
+    /// Directly converts characters to UTF-8 byte representation.
+    pub fn derive_str(s: &str) -> Identity {
+        Self::derive(s.as_bytes())
+    }
+}
+
+impl PublicKey {
+    pub fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
+        let mut res = [0u8; PUBLICKEYSIZE];
+        let (g, g0, g1, v1, v2, v3, v4, omega) =
+            mut_array_refs![&mut res, 48, 48, 48, 48, 48, 48, 48, 288];
+        *g = self.g.to_compressed();
+        *g0 = self.g0.to_compressed();
+        *g1 = self.g1.to_compressed();
+        *v1 = self.v1.to_compressed();
+        *v2 = self.v2.to_compressed();
+        *v3 = self.v3.to_compressed();
+        *v4 = self.v4.to_compressed();
+        *omega = self.omega.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; PUBLICKEYSIZE]) -> CtOption<Self> {
+        let (g, g0, g1, v1, v2, v3, v4, omega) =
+            array_refs![bytes, 48, 48, 48, 48, 48, 48, 48, 288];
+
+        let g = G1Affine::from_compressed(g);
+        let g0 = G1Affine::from_compressed(g0);
+        let g1 = G1Affine::from_compressed(g1);
+        let v1 = G1Affine::from_compressed(v1);
+        let v2 = G1Affine::from_compressed(v2);
+        let v3 = G1Affine::from_compressed(v3);
+        let v4 = G1Affine::from_compressed(v4);
+        let omega = Gt::from_compressed(omega);
+
+        let is_some = g.is_some()
+            & g0.is_some()
+            & g1.is_some()
+            & v1.is_some()
+            & v2.is_some()
+            & v3.is_some()
+            & v4.is_some()
+            & omega.is_some();
+
+        CtOption::new(
+            PublicKey {
+                g: g.unwrap_or(G1Affine::default()),
+                g0: g0.unwrap_or(G1Affine::default()),
+                g1: g1.unwrap_or(G1Affine::default()),
+                v1: v1.unwrap_or(G1Affine::default()),
+                v2: v2.unwrap_or(G1Affine::default()),
+                v3: v3.unwrap_or(G1Affine::default()),
+                v4: v4.unwrap_or(G1Affine::default()),
+                omega: omega.unwrap_or(Gt::default()),
+            },
+            is_some,
+        )
+    }
+}
+
+impl SecretKey {
+    pub fn to_bytes(&self) -> [u8; SECRETKEYSIZE] {
+        let mut res = [0u8; SECRETKEYSIZE];
+        let (ghat, y0, y1, w, t1, t2, t3, t4) =
+            mut_array_refs![&mut res, 96, 32, 32, 32, 32, 32, 32, 32];
+        *ghat = self.ghat.to_compressed();
+        *y0 = self.y0.to_bytes();
+        *y1 = self.y1.to_bytes();
+        *w = self.w.to_bytes();
+        *t1 = self.t1.to_bytes();
+        *t2 = self.t2.to_bytes();
+        *t3 = self.t3.to_bytes();
+        *t4 = self.t4.to_bytes();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; SECRETKEYSIZE]) -> CtOption<Self> {
+        let (ghat, y0, y1, w, t1, t2, t3, t4) = array_refs![bytes, 96, 32, 32, 32, 32, 32, 32, 32];
+
+        let ghat = G2Affine::from_compressed(ghat);
+        let y0 = Scalar::from_bytes(y0);
+        let y1 = Scalar::from_bytes(y1);
+        let w = Scalar::from_bytes(w);
+        let t1 = Scalar::from_bytes(t1);
+        let t2 = Scalar::from_bytes(t2);
+        let t3 = Scalar::from_bytes(t3);
+        let t4 = Scalar::from_bytes(t4);
+
+        let is_some = ghat.is_some()
+            & y0.is_some()
+            & y1.is_some()
+            & w.is_some()
+            & t1.is_some()
+            & t2.is_some()
+            & t3.is_some()
+            & t4.is_some();
+
+        CtOption::new(
+            SecretKey {
+                ghat: ghat.unwrap_or(G2Affine::default()),
+                y0: y0.unwrap_or(Scalar::zero()),
+                y1: y1.unwrap_or(Scalar::zero()),
+                w: w.unwrap_or(Scalar::zero()),
+                t1: t1.unwrap_or(Scalar::zero()),
+                t2: t2.unwrap_or(Scalar::zero()),
+                t3: t3.unwrap_or(Scalar::zero()),
+                t4: t4.unwrap_or(Scalar::zero()),
+            },
+            is_some,
+        )
+    }
+}
+
+impl UserSecretKey {
+    pub fn to_bytes(&self) -> [u8; USERSECRETKEYSIZE] {
+        let mut res = [0u8; USERSECRETKEYSIZE];
+        let (d0, d1, d2, d3, d4) = mut_array_refs![&mut res, 96, 96, 96, 96, 96];
+        *d0 = self.d0.to_compressed();
+        *d1 = self.d1.to_compressed();
+        *d2 = self.d2.to_compressed();
+        *d3 = self.d3.to_compressed();
+        *d4 = self.d4.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; USERSECRETKEYSIZE]) -> CtOption<Self> {
+        let (d0, d1, d2, d3, d4) = array_refs![bytes, 96, 96, 96, 96, 96];
+
+        let d0 = G2Affine::from_compressed(d0);
+        let d1 = G2Affine::from_compressed(d1);
+        let d2 = G2Affine::from_compressed(d2);
+        let d3 = G2Affine::from_compressed(d3);
+        let d4 = G2Affine::from_compressed(d4);
+
+        d0.and_then(|d0| {
+            d1.and_then(|d1| {
+                d2.and_then(|d2| {
+                    d3.and_then(|d3| d4.map(|d4| UserSecretKey { d0, d1, d2, d3, d4 }))
+                })
+            })
+        })
+    }
+}
+
+impl Message {
+    /// Generate a random point on the paired curve.
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self(rand_gt(rng))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 288] {
+        self.0.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
+        Gt::from_compressed(bytes).map(Message)
+    }
+}
+
+impl CipherText {
+    pub fn to_bytes(&self) -> [u8; CIPHERTEXTSIZE] {
+        let mut res = [0u8; CIPHERTEXTSIZE];
+        let (cprime, c0, c1, c2, c3, c4) = mut_array_refs![&mut res, 288, 48, 48, 48, 48, 48];
+        *cprime = self.cprime.to_compressed();
+        *c0 = self.c0.to_compressed();
+        *c1 = self.c1.to_compressed();
+        *c2 = self.c2.to_compressed();
+        *c3 = self.c3.to_compressed();
+        *c4 = self.c4.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; CIPHERTEXTSIZE]) -> CtOption<Self> {
+        let (cprime, c0, c1, c2, c3, c4) = array_refs![bytes, 288, 48, 48, 48, 48, 48];
+
+        let cprime = Gt::from_compressed(cprime);
+        let c0 = G1Affine::from_compressed(c0);
+        let c1 = G1Affine::from_compressed(c1);
+        let c2 = G1Affine::from_compressed(c2);
+        let c3 = G1Affine::from_compressed(c3);
+        let c4 = G1Affine::from_compressed(c4);
+
+        cprime.and_then(|cprime| {
+            c0.and_then(|c0| {
+                c1.and_then(|c1| {
+                    c2.and_then(|c2| {
+                        c3.and_then(|c3| {
+                            c4.map(|c4| CipherText {
+                                cprime,
+                                c0,
+                                c1,
+                                c2,
+                                c3,
+                                c4,
+                            })
+                        })
+                    })
+                })
+            })
+        })
+    }
+}
+
+impl KemCipherText {
+    pub fn to_bytes(&self) -> [u8; KEMCIPHERTEXTSIZE] {
+        let mut res = [0u8; KEMCIPHERTEXTSIZE];
+        let (c, tag) = mut_array_refs![&mut res, CIPHERTEXTSIZE, 32];
+        *c = self.c.to_bytes();
+        *tag = self.tag;
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; KEMCIPHERTEXTSIZE]) -> CtOption<Self> {
+        let (c, tag) = array_refs![bytes, CIPHERTEXTSIZE, 32];
+
+        CipherText::from_bytes(c).map(|c| KemCipherText { c, tag: *tag })
+    }
+}
+
+impl ConditionallySelectable for CipherText {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        CipherText {
+            cprime: Gt::conditional_select(&a.cprime, &b.cprime, choice),
+            c0: G1Affine::conditional_select(&a.c0, &b.c0, choice),
+            c1: G1Affine::conditional_select(&a.c1, &b.c1, choice),
+            c2: G1Affine::conditional_select(&a.c2, &b.c2, choice),
+            c3: G1Affine::conditional_select(&a.c3, &b.c3, choice),
+            c4: G1Affine::conditional_select(&a.c4, &b.c4, choice),
+        }
+    }
+}
+
+impl core::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("SecretKey(<redacted>)")
+    }
+}
+
+impl core::fmt::Debug for UserSecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("UserSecretKey(<redacted>)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    #[allow(dead_code)]
+    struct DefaultSubResults {
+        kid: Identity,
+        m: Message,
+        pk: PublicKey,
+        sk: SecretKey,
+        usk: UserSecretKey,
+        c: CipherText,
+    }
+
+    fn perform_default() -> DefaultSubResults {
+        let mut rng = rand::thread_rng();
+
+        let id = ID.as_bytes();
+        let kid = Identity::derive(id);
+
+        let m = Message::generate(&mut rng);
+
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let c = encrypt(&pk, &kid, &m, &mut rng);
+
+        DefaultSubResults {
+            kid,
+            m,
+            pk,
+            sk,
+            usk,
+            c,
+        }
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt() {
+        let results = perform_default();
+        let m2 = decrypt(&results.usk, &results.c);
+
+        assert_eq!(results.m, m2);
+    }
+
+    #[test]
+    fn debug_output() {
+        let results = perform_default();
+
+        assert_eq!(std::format!("{:?}", results.sk), "SecretKey(<redacted>)");
+        assert_eq!(
+            std::format!("{:?}", results.usk),
+            "UserSecretKey(<redacted>)"
+        );
+    }
+
+    #[test]
+    fn trial_decryption() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let ids = ["email:someone@else.nl", ID, "email:another@one.nl"];
+        let usks: std::vec::Vec<_> = ids
+            .iter()
+            .map(|id| {
+                extract_usk(
+                    &results.pk,
+                    &results.sk,
+                    &Identity::derive_str(id),
+                    &mut rng,
+                )
+            })
+            .collect();
+
+        let (c, k) = encapsulate(&results.pk, &results.kid, &mut rng);
+        let found: std::vec::Vec<_> = usks.iter().map(|usk| decapsulate(usk, &c)).collect();
+        assert!(bool::from(found[0].is_none()));
+        assert_eq!(found[1].unwrap(), k);
+        assert!(bool::from(found[2].is_none()));
+
+        let mut bytes = c.to_bytes();
+        assert_eq!(KemCipherText::from_bytes(&bytes).unwrap(), c);
+        bytes[CIPHERTEXTSIZE] ^= 1;
+        let tampered = KemCipherText::from_bytes(&bytes).unwrap();
+        assert!(bool::from(decapsulate(&usks[1], &tampered).is_none()));
+    }
+
+    #[test]
+    fn eq_serialize_deserialize() {
+        let result = perform_default();
+
+        assert_eq!(result.m, Message::from_bytes(&result.m.to_bytes()).unwrap());
+        assert_eq!(
+            result.pk,
+            PublicKey::from_bytes(&result.pk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.sk,
+            SecretKey::from_bytes(&result.sk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.usk,
+            UserSecretKey::from_bytes(&result.usk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.c,
+            CipherText::from_bytes(&result.c.to_bytes()).unwrap()
+        );
+    }
+}
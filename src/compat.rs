@@ -0,0 +1,207 @@
+//! Migration of serialized Waters public keys and ciphertexts from previous format versions.
+//!
+//! Every format version of the versioned byte representation has a module `vN`, with types
+//! that parse the layout of that version and `upgrade` to the current types of the `waters` module.
+//! `upgrade_public_key` and `upgrade_ciphertext` read the format version from a versioned byte
+//! representation and dispatch to the corresponding module, such that data persisted using
+//! `to_versioned_bytes` by any release remains readable.
+//!
+//! Version 1 is the current format, so its types upgrade without conversion. When the format
+//! changes, the previous layout is kept here as a new module.
+//!
+//! Data persisted using the unversioned `to_bytes` of release 0.1 has no tag or version,
+//! and is migrated using the functions of the `v0` module instead.
+
+use crate::error::ParseError;
+use crate::waters::{self, FORMAT_VERSION, SCHEME_TAG};
+
+/// The unversioned format of release 0.1, as produced by its `to_bytes`.
+///
+/// The layout is the same as that of `to_bytes` today, but release 0.2 fixed the entanglement
+/// of identities, which changed the user secret key and ciphertexts for every identity.
+/// Public keys and secret keys of the PKG are not affected, and upgrade without conversion.
+/// User secret keys and ciphertexts are rejected with `ParseError::Incompatible`:
+/// keys must be extracted again, and messages encrypted again.
+///
+/// Unlike `from_bytes`, these functions do not run in constant time.
+pub mod v0 {
+    use crate::error::ParseError;
+    use crate::waters::{
+        self, CIPHERTEXT_BYTES, PUBLIC_KEY_BYTES, SECRET_KEY_BYTES, USER_SECRET_KEY_BYTES,
+    };
+
+    /// Deserialize a public key of release 0.1 to the current type.
+    pub fn upgrade_public_key(
+        bytes: &[u8; PUBLIC_KEY_BYTES],
+    ) -> Result<waters::PublicKey, ParseError> {
+        Ok(waters::PublicKey::try_from_bytes(bytes)?)
+    }
+
+    /// Deserialize a secret key of release 0.1 to the current type.
+    pub fn upgrade_secret_key(
+        bytes: &[u8; SECRET_KEY_BYTES],
+    ) -> Result<waters::SecretKey, ParseError> {
+        Ok(waters::SecretKey::try_from_bytes(bytes)?)
+    }
+
+    /// Reject a user secret key of release 0.1, which must be extracted again.
+    ///
+    /// Fails with `ParseError::Incompatible` if the bytes are a valid user secret key.
+    pub fn upgrade_user_secret_key(
+        bytes: &[u8; USER_SECRET_KEY_BYTES],
+    ) -> Result<waters::UserSecretKey, ParseError> {
+        waters::UserSecretKey::try_from_bytes(bytes)?;
+        Err(ParseError::Incompatible(0))
+    }
+
+    /// Reject a ciphertext of release 0.1, of which the message must be encrypted again.
+    ///
+    /// Fails with `ParseError::Incompatible` if the bytes are a valid ciphertext.
+    pub fn upgrade_ciphertext(
+        bytes: &[u8; CIPHERTEXT_BYTES],
+    ) -> Result<waters::CipherText, ParseError> {
+        waters::CipherText::try_from_bytes(bytes)?;
+        Err(ParseError::Incompatible(0))
+    }
+}
+
+/// Format version 1, the first versioned format.
+pub mod v1 {
+    use core::convert::TryFrom;
+    use subtle::CtOption;
+
+    use crate::error::ParseError;
+    use crate::waters::{self, CIPHERTEXT_BYTES, PUBLIC_KEY_BYTES};
+
+    /// Public key in the layout of format version 1.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct PublicKey(waters::PublicKey);
+
+    /// Ciphertext in the layout of format version 1.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct CipherText(waters::CipherText);
+
+    impl PublicKey {
+        pub fn from_bytes(bytes: &[u8; PUBLIC_KEY_BYTES]) -> CtOption<Self> {
+            waters::PublicKey::from_bytes(bytes).map(PublicKey)
+        }
+
+        /// Deserialize the body of a versioned byte representation, without the tag and version.
+        pub fn from_body(body: &[u8]) -> Result<Self, ParseError> {
+            waters::PublicKey::try_from(body).map(PublicKey)
+        }
+
+        /// Convert to the current public key.
+        pub fn upgrade(self) -> waters::PublicKey {
+            self.0
+        }
+    }
+
+    impl CipherText {
+        pub fn from_bytes(bytes: &[u8; CIPHERTEXT_BYTES]) -> CtOption<Self> {
+            waters::CipherText::from_bytes(bytes).map(CipherText)
+        }
+
+        /// Deserialize the body of a versioned byte representation, without the tag and version.
+        pub fn from_body(body: &[u8]) -> Result<Self, ParseError> {
+            waters::CipherText::try_from(body).map(CipherText)
+        }
+
+        /// Convert to the current ciphertext.
+        pub fn upgrade(self) -> waters::CipherText {
+            self.0
+        }
+    }
+}
+
+/// Split a versioned byte representation into its format version and body.
+fn split_versioned(bytes: &[u8]) -> Result<(u8, &[u8]), ParseError> {
+    match bytes {
+        [SCHEME_TAG, version, body @ ..] => Ok((*version, body)),
+        [tag, _, ..] => Err(ParseError::Scheme(*tag)),
+        _ => Err(ParseError::Length {
+            expected: 2,
+            found: bytes.len(),
+        }),
+    }
+}
+
+/// Deserialize a versioned public key of any supported format version to the current type.
+pub fn upgrade_public_key(bytes: &[u8]) -> Result<waters::PublicKey, ParseError> {
+    match split_versioned(bytes)? {
+        (1, body) => v1::PublicKey::from_body(body).map(v1::PublicKey::upgrade),
+        (version, _) => Err(ParseError::UnsupportedVersion(version)),
+    }
+}
+
+/// Deserialize a versioned ciphertext of any supported format version to the current type.
+pub fn upgrade_ciphertext(bytes: &[u8]) -> Result<waters::CipherText, ParseError> {
+    match split_versioned(bytes)? {
+        (1, body) => v1::CipherText::from_body(body).map(v1::CipherText::upgrade),
+        (version, _) => Err(ParseError::UnsupportedVersion(version)),
+    }
+}
+
+// Every format version up to the current one must be handled above.
+const _: () = assert!(FORMAT_VERSION == 1);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::DeserializeError;
+    use crate::waters::{encrypt, extract_usk, setup, Identity, Message};
+
+    #[test]
+    fn upgrade_roundtrip() {
+        let mut rng = rand::thread_rng();
+        let (pk, _) = setup(&mut rng);
+        let kid = Identity::derive_str("email:w.geraedts@sarif.nl");
+        let c = encrypt(&pk, &kid, &Message::generate(&mut rng), &mut rng);
+
+        assert_eq!(upgrade_public_key(&pk.to_versioned_bytes()), Ok(pk));
+        assert_eq!(upgrade_ciphertext(&c.to_versioned_bytes()), Ok(c));
+        assert_eq!(
+            v1::CipherText::from_bytes(&c.to_bytes()).unwrap().upgrade(),
+            c
+        );
+
+        let mut bytes = c.to_versioned_bytes();
+        bytes[1] = 2;
+        assert_eq!(
+            upgrade_ciphertext(&bytes),
+            Err(ParseError::UnsupportedVersion(2))
+        );
+    }
+
+    #[test]
+    fn upgrade_unversioned() {
+        let mut rng = rand::thread_rng();
+        let (pk, sk) = setup(&mut rng);
+        let kid = Identity::derive_str("email:w.geraedts@sarif.nl");
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+        let c = encrypt(&pk, &kid, &Message::generate(&mut rng), &mut rng);
+
+        assert_eq!(v0::upgrade_public_key(&pk.to_bytes()), Ok(pk));
+        assert_eq!(
+            v0::upgrade_secret_key(&sk.to_bytes()).unwrap().to_bytes(),
+            sk.to_bytes()
+        );
+        assert_eq!(
+            v0::upgrade_user_secret_key(&usk.to_bytes()),
+            Err(ParseError::Incompatible(0))
+        );
+        assert_eq!(
+            v0::upgrade_ciphertext(&c.to_bytes()),
+            Err(ParseError::Incompatible(0))
+        );
+
+        let mut bytes = c.to_bytes();
+        bytes[288] &= 0x7f;
+        assert_eq!(
+            v0::upgrade_ciphertext(&bytes),
+            Err(ParseError::Deserialize(DeserializeError::NonCanonical(
+                "c2"
+            )))
+        );
+    }
+}
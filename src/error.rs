@@ -0,0 +1,140 @@
+//! Errors returned by the fallible, non-constant time APIs of this library.
+
+use core::fmt;
+
+/// Error returned when a byte representation could not be deserialized.
+///
+/// Each variant contains the name of the field that failed to decompress.
+/// For points on G1 and G2, the variant tells why the point was rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeserializeError {
+    /// The field is not a canonical compressed point encoding: the compression flag is not set,
+    /// the point at infinity is not encoded as all zeros, or a coordinate is not reduced
+    /// modulo the field prime.
+    NonCanonical(&'static str),
+    /// The field encodes an x-coordinate for which there is no point on the curve.
+    OffCurve(&'static str),
+    /// The field encodes a point on the curve outside of the prime order subgroup.
+    WrongSubgroup(&'static str),
+    /// The field is not a valid compressed element of Gt.
+    GtPoint(&'static str),
+    /// The field encodes the point at infinity, which is not allowed for that field.
+    IdentityPoint(&'static str),
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeserializeError::NonCanonical(field) => {
+                write!(f, "non-canonical point encoding for field {}", field)
+            }
+            DeserializeError::OffCurve(field) => {
+                write!(f, "point not on the curve for field {}", field)
+            }
+            DeserializeError::WrongSubgroup(field) => {
+                write!(
+                    f,
+                    "point not in the prime order subgroup for field {}",
+                    field
+                )
+            }
+            DeserializeError::GtPoint(field) => write!(f, "invalid Gt element for field {}", field),
+            DeserializeError::IdentityPoint(field) => {
+                write!(f, "point at infinity for field {}", field)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DeserializeError {}
+
+/// Error returned when parsing the textual base64 representation, a byte slice of unknown length,
+/// or a versioned byte representation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The string is not valid unpadded url-safe base64.
+    Base64,
+    /// The decoded byte representation has the wrong length.
+    Length { expected: usize, found: usize },
+    /// The decoded byte representation could not be deserialized.
+    Deserialize(DeserializeError),
+    /// The versioned byte representation is tagged with another scheme.
+    Scheme(u8),
+    /// The versioned byte representation uses a format version that is not supported.
+    UnsupportedVersion(u8),
+    /// The byte representation uses a format version whose contents can not be converted
+    /// to the current format, and must be created again.
+    Incompatible(u8),
+}
+
+impl From<DeserializeError> for ParseError {
+    fn from(e: DeserializeError) -> Self {
+        ParseError::Deserialize(e)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Base64 => write!(f, "invalid base64"),
+            ParseError::Length { expected, found } => {
+                write!(f, "expected {} bytes, found {}", expected, found)
+            }
+            ParseError::Deserialize(e) => e.fmt(f),
+            ParseError::Scheme(t) => write!(f, "unexpected scheme tag {}", t),
+            ParseError::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            ParseError::Incompatible(v) => write!(f, "incompatible format version {}", v),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+/// Error returned when a hybrid encrypted blob could not be decrypted.
+#[cfg(feature = "aead")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HybridError {
+    /// The blob is shorter than its header.
+    Truncated,
+    /// The blob uses a framing version that is not supported.
+    UnsupportedVersion(u8),
+    /// The IBE ciphertext in the blob could not be deserialized.
+    CipherText(DeserializeError),
+    /// The blob was not encrypted for this user secret key, or has been tampered with.
+    Decryption,
+}
+
+#[cfg(feature = "aead")]
+impl fmt::Display for HybridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HybridError::Truncated => write!(f, "blob is truncated"),
+            HybridError::UnsupportedVersion(v) => write!(f, "unsupported blob version {}", v),
+            HybridError::CipherText(e) => write!(f, "invalid IBE ciphertext: {}", e),
+            HybridError::Decryption => write!(f, "decryption failed"),
+        }
+    }
+}
+
+#[cfg(all(feature = "aead", feature = "std"))]
+impl std::error::Error for HybridError {}
+
+/// Error returned when a signcrypted ciphertext could not be unsigncrypted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SigncryptionError {
+    /// The signature is not valid for the sender, recipient identity, ciphertext and message.
+    Signature,
+}
+
+impl fmt::Display for SigncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SigncryptionError::Signature => write!(f, "invalid sender signature"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SigncryptionError {}
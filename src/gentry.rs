@@ -0,0 +1,396 @@
+//! Anonymous Identity Based Encryption Gentry scheme on the [BLS12-381 pairing-friendly elliptic curve](https://github.com/zkcrypto/bls12_381).
+//!  * From: "[Practical Identity-Based Encryption Without Random Oracles](https://link.springer.com/chapter/10.1007/11761679_27)"
+//!  * Published in: EUROCRYPT, 2006
+//!  * Adapted to asymmetric pairings
+//!
+//! Uses [SHA3-512](https://crates.io/crates/tiny-keccak) for hashing to identities.
+//!
+//! Provides adaptive-identity security in the standard model with a tight reduction,
+//! under the truncated q-ABDHE assumption. Only the basic, chosen-plaintext secure variant
+//! is implemented. Unlike Waters, the public key has a constant size.
+//!
+//! Ciphertexts hide the identity they were encrypted for, as the public key contains no
+//! elements of G2 that could be used to test a ciphertext against a guessed identity.
+//!
+//! Every identity must always receive the same randomness `r` in its user secret key.
+//! To do so without keeping state, the PKG derives it from the identity using a secret key
+//! generated during setup, so extraction is deterministic.
+//!
+//! The structure of the byte serialisation of the various datastructures is not guaranteed
+//! to remain constant between releases of this library.
+//! All operations in this library are implemented to run in constant time.
+
+use arrayref::{array_refs, mut_array_refs};
+use rand::{CryptoRng, RngCore};
+use subtle::CtOption;
+
+use crate::util::*;
+use irmaseal_curve::{G1Affine, G2Affine, Gt, Scalar};
+
+const PUBLICKEYSIZE: usize = 2 * 48 + 2 * 288;
+const SECRETKEYSIZE: usize = 32 + 2 * 96 + 32;
+const USERSECRETKEYSIZE: usize = 32 + 96;
+const CIPHERTEXTSIZE: usize = 48 + 2 * 288;
+
+const IDENTITY_DST: &[u8] = b"ibe-gentry-identity";
+const RANDOMNESS_DST: &[u8] = b"ibe-gentry-randomness";
+
+/// Public key parameters generated by the PKG used to encrypt messages.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PublicKey {
+    g: G1Affine,
+    g1: G1Affine,
+    z: Gt,
+    zh: Gt,
+}
+
+/// Secret key parameters generated by the PKG used to extract user secret keys.
+///
+/// Contains the G2 points `ghat` and `h`, as these may not be published without losing anonymity,
+/// and the key from which the randomness for every identity is derived.
+/// The `Debug` output is redacted.
+#[derive(Clone, PartialEq)]
+pub struct SecretKey {
+    alpha: Scalar,
+    ghat: G2Affine,
+    h: G2Affine,
+    k: [u8; 32],
+}
+
+/// The randomness for an identity and a point on G2 that form the user secret key.
+/// The `Debug` output is redacted.
+#[derive(Clone, PartialEq)]
+pub struct UserSecretKey {
+    r: Scalar,
+    hid: G2Affine,
+}
+
+/// Field element for an identity.
+///
+/// Effectively a hash of an identity, mapped to the curve field.
+/// Together with the public key parameters generated by the PKG forms the user public key.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Identity(Scalar);
+
+/// A point on the paired curve that can be encrypted and decrypted.
+///
+/// You can use the byte representation to derive an AES key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Message(Gt);
+
+/// Encrypted message. Can only be decrypted with an user secret key.
+///
+/// Does not reveal the identity it was encrypted for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CipherText {
+    u: G1Affine,
+    v: Gt,
+    w: Gt,
+}
+
+/// The Gentry scheme, implementing the common `IbeScheme` interface.
+#[derive(Clone, Copy, Debug)]
+pub struct Gentry;
+
+impl_ibe_scheme!(Gentry);
+
+/// Generate a keypair used by the Private Key Generator (PKG).
+pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+    let g: G1Affine = rand_g1(rng).into();
+    let ghat: G2Affine = rand_g2(rng).into();
+    let h: G2Affine = rand_g2(rng).into();
+
+    let alpha = rand_scalar(rng);
+    let mut k = [0u8; 32];
+    rng.fill_bytes(&mut k);
+
+    let pk = PublicKey {
+        g,
+        g1: (g * alpha).into(),
+        z: irmaseal_curve::pairing(&g, &ghat),
+        zh: irmaseal_curve::pairing(&g, &h),
+    };
+    let sk = SecretKey { alpha, ghat, h, k };
+
+    (pk, sk)
+}
+
+/// Extract the user secret key for a given identity.
+///
+/// Deterministic: extracting for the same identity always results in the same key.
+/// Fails with negligible probability, if the identity happens to equal the master secret,
+/// in which case the key does not decrypt.
+/// Takes the public key and a random number generator to match the other schemes, but needs neither.
+pub fn extract_usk<R: RngCore + CryptoRng>(
+    _pk: &PublicKey,
+    sk: &SecretKey,
+    v: &Identity,
+    _rng: &mut R,
+) -> UserSecretKey {
+    let r = Scalar::from_bytes_wide(&sha3_512_dst(RANDOMNESS_DST, &[&sk.k, &v.0.to_bytes()]));
+    let inv = (sk.alpha - v.0).invert().unwrap_or(Scalar::zero());
+    let hid = ((sk.h - sk.ghat * r) * inv).into();
+
+    UserSecretKey { r, hid }
+}
+
+/// Encrypt a message using the PKG public key and an identity.
+pub fn encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let s = rand_scalar(rng);
+
+    let u = ((pk.g1 - pk.g * v.0) * s).into();
+    let v = pk.z * s;
+    let w = m.0 - pk.zh * s;
+
+    CipherText { u, v, w }
+}
+
+/// Decrypt ciphertext to a message using a user secret key.
+///
+/// Decrypting with the user secret key of another identity results in a random message.
+pub fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
+    let m = c.w + irmaseal_curve::pairing(&c.u, &usk.hid) + c.v * usk.r;
+    Message(m)
+}
+
+impl Identity {
+    /// Hash a byte slice to an Identity field element, which acts as a user public key.
+    /// Uses a domain-separated sha3-512 internally.
+    pub fn derive(b: &[u8]) -> Identity {
+        Identity(Scalar::from_bytes_wide(&sha3_512_dst(IDENTITY_DST, &[b])))
+    }
+
+    /// Hash a string slice to an Identity field element.
+    /// Directly converts characters to UTF-8 byte representation.
+    pub fn derive_str(s: &str) -> Identity {
+        Self::derive(s.as_bytes())
+    }
+}
+
+impl PublicKey {
+    pub fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
+        let mut res = [0u8; PUBLICKEYSIZE];
+        let (g, g1, z, zh) = mut_array_refs![&mut res, 48, 48, 288, 288];
+        *g = self.g.to_compressed();
+        *g1 = self.g1.to_compressed();
+        *z = self.z.to_compressed();
+        *zh = self.zh.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; PUBLICKEYSIZE]) -> CtOption<Self> {
+        let (g, g1, z, zh) = array_refs![bytes, 48, 48, 288, 288];
+
+        let g = G1Affine::from_compressed(g);
+        let g1 = G1Affine::from_compressed(g1);
+        let z = Gt::from_compressed(z);
+        let zh = Gt::from_compressed(zh);
+
+        g.and_then(|g| g1.and_then(|g1| z.and_then(|z| zh.map(|zh| PublicKey { g, g1, z, zh }))))
+    }
+}
+
+impl SecretKey {
+    pub fn to_bytes(&self) -> [u8; SECRETKEYSIZE] {
+        let mut res = [0u8; SECRETKEYSIZE];
+        let (alpha, ghat, h, k) = mut_array_refs![&mut res, 32, 96, 96, 32];
+        *alpha = self.alpha.to_bytes();
+        *ghat = self.ghat.to_compressed();
+        *h = self.h.to_compressed();
+        *k = self.k;
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; SECRETKEYSIZE]) -> CtOption<Self> {
+        let (alpha, ghat, h, k) = array_refs![bytes, 32, 96, 96, 32];
+
+        let alpha = Scalar::from_bytes(alpha);
+        let ghat = G2Affine::from_compressed(ghat);
+        let h = G2Affine::from_compressed(h);
+
+        alpha.and_then(|alpha| {
+            ghat.and_then(|ghat| {
+                h.map(|h| SecretKey {
+                    alpha,
+                    ghat,
+                    h,
+                    k: *k,
+                })
+            })
+        })
+    }
+}
+
+impl UserSecretKey {
+    pub fn to_bytes(&self) -> [u8; USERSECRETKEYSIZE] {
+        let mut res = [0u8; USERSECRETKEYSIZE];
+        let (r, hid) = mut_array_refs![&mut res, 32, 96];
+        *r = self.r.to_bytes();
+        *hid = self.hid.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; USERSECRETKEYSIZE]) -> CtOption<Self> {
+        let (r, hid) = array_refs![bytes, 32, 96];
+
+        let r = Scalar::from_bytes(r);
+        let hid = G2Affine::from_compressed(hid);
+
+        r.and_then(|r| hid.map(|hid| UserSecretKey { r, hid }))
+    }
+}
+
+impl Message {
+    /// Generate a random point on the paired curve.
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self(rand_gt(rng))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 288] {
+        self.0.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
+        Gt::from_compressed(bytes).map(Message)
+    }
+}
+
+impl CipherText {
+    pub fn to_bytes(&self) -> [u8; CIPHERTEXTSIZE] {
+        let mut res = [0u8; CIPHERTEXTSIZE];
+        let (u, v, w) = mut_array_refs![&mut res, 48, 288, 288];
+        *u = self.u.to_compressed();
+        *v = self.v.to_compressed();
+        *w = self.w.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; CIPHERTEXTSIZE]) -> CtOption<Self> {
+        let (u, v, w) = array_refs![bytes, 48, 288, 288];
+
+        let u = G1Affine::from_compressed(u);
+        let v = Gt::from_compressed(v);
+        let w = Gt::from_compressed(w);
+
+        u.and_then(|u| v.and_then(|v| w.map(|w| CipherText { u, v, w })))
+    }
+}
+
+impl core::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("SecretKey(<redacted>)")
+    }
+}
+
+impl core::fmt::Debug for UserSecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("UserSecretKey(<redacted>)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    #[allow(dead_code)]
+    struct DefaultSubResults {
+        kid: Identity,
+        m: Message,
+        pk: PublicKey,
+        sk: SecretKey,
+        usk: UserSecretKey,
+        c: CipherText,
+    }
+
+    fn perform_default() -> DefaultSubResults {
+        let mut rng = rand::thread_rng();
+
+        let id = ID.as_bytes();
+        let kid = Identity::derive(id);
+
+        let m = Message::generate(&mut rng);
+
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let c = encrypt(&pk, &kid, &m, &mut rng);
+
+        DefaultSubResults {
+            kid,
+            m,
+            pk,
+            sk,
+            usk,
+            c,
+        }
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt() {
+        let results = perform_default();
+        let m2 = decrypt(&results.usk, &results.c);
+
+        assert_eq!(results.m, m2);
+    }
+
+    #[test]
+    fn debug_output() {
+        let results = perform_default();
+
+        assert_eq!(std::format!("{:?}", results.sk), "SecretKey(<redacted>)");
+        assert_eq!(
+            std::format!("{:?}", results.usk),
+            "UserSecretKey(<redacted>)"
+        );
+    }
+
+    #[test]
+    fn deterministic_extraction() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        assert_eq!(
+            results.usk,
+            extract_usk(&results.pk, &results.sk, &results.kid, &mut rng)
+        );
+
+        let other = extract_usk(
+            &results.pk,
+            &results.sk,
+            &Identity::derive_str("email:someone@else.nl"),
+            &mut rng,
+        );
+        assert_ne!(results.usk, other);
+        assert_ne!(results.m, decrypt(&other, &results.c));
+    }
+
+    #[test]
+    fn eq_serialize_deserialize() {
+        let result = perform_default();
+
+        assert_eq!(result.m, Message::from_bytes(&result.m.to_bytes()).unwrap());
+        assert_eq!(
+            result.pk,
+            PublicKey::from_bytes(&result.pk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.sk,
+            SecretKey::from_bytes(&result.sk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.usk,
+            UserSecretKey::from_bytes(&result.usk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.c,
+            CipherText::from_bytes(&result.c.to_bytes()).unwrap()
+        );
+    }
+}
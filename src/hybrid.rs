@@ -0,0 +1,378 @@
+//! Hybrid encryption of arbitrary-length messages, combining the Waters IBE-KEM with AES-256-GCM.
+//!
+//! The shared secret produced by `waters::encapsulate` is used as the AES-256-GCM key.
+//! Encrypted blobs have the following framing, which is stable across releases:
+//!
+//! | Field          | Size (bytes)     |
+//! |----------------|------------------|
+//! | version (`1`)  | 1                |
+//! | IBE ciphertext | 432              |
+//! | nonce          | 12               |
+//! | AEAD output    | plaintext + 16   |
+//!
+//! The version, IBE ciphertext and nonce are authenticated as associated data,
+//! followed by the associated data supplied by the caller, if any.
+//! This associated data is not contained in the blob, so it can not be stripped or replaced
+//! without decryption failing. Without associated data, the blob is the same as before
+//! associated data was supported.
+//!
+//! For plaintexts too large to keep in memory, `HybridEncryptor` and `HybridDecryptor`
+//! encrypt a stream in segments using the STREAM construction. The IBE ciphertext is returned
+//! separately, followed by segments of `SEGMENT_SIZE` bytes of plaintext plus a 16-byte tag each,
+//! the last of which may be shorter. The AES-256-GCM key is derived from the shared secret,
+//! and the nonce of every segment consists of 7 zero bytes, its index as a 32-bit big-endian
+//! integer, and a byte that is `1` for the last segment and `0` otherwise.
+//! As the key is fresh for every stream, nonces are never reused, and reordering, dropping
+//! or truncating segments makes decryption fail.
+//!
+//! Requires the `aead` feature.
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use alloc::vec::Vec;
+use arrayref::array_ref;
+use rand::{CryptoRng, RngCore};
+
+use crate::error::HybridError;
+use crate::util::sha3_256_dst;
+use crate::waters::{
+    decapsulate, encapsulate, CipherText, Identity, PublicKey, UserSecretKey, CIPHERTEXT_BYTES,
+};
+
+/// Version of the wire framing produced by `hybrid_encrypt`.
+pub const VERSION: u8 = 1;
+
+const NONCESIZE: usize = 12;
+const HEADERSIZE: usize = 1 + CIPHERTEXT_BYTES + NONCESIZE;
+const TAGSIZE: usize = 16;
+
+/// Number of plaintext bytes in every segment of a stream, except the last.
+pub const SEGMENT_SIZE: usize = 64 * 1024;
+
+const STREAM_KEY_DST: &[u8] = b"ibe-hybrid-stream-key";
+
+/// Encrypt an arbitrary-length plaintext for an identity using the PKG public key.
+///
+/// The associated data `aad` is authenticated but not encrypted, and must be passed
+/// to `hybrid_decrypt` unchanged. Use an empty slice if there is none.
+///
+/// # Panics
+/// If the plaintext is too long for AES-GCM, which is about 64 GiB.
+pub fn hybrid_encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    plaintext: &[u8],
+    aad: &[u8],
+    rng: &mut R,
+) -> Vec<u8> {
+    let (c, k) = encapsulate(pk, v, rng);
+
+    let mut nonce = [0u8; NONCESIZE];
+    rng.fill_bytes(&mut nonce);
+
+    let mut blob = Vec::with_capacity(HEADERSIZE + plaintext.len() + 16);
+    blob.push(VERSION);
+    blob.extend_from_slice(&c.to_bytes());
+    blob.extend_from_slice(&nonce);
+
+    let aad = associated_data(&blob, aad);
+    let payload = Payload {
+        msg: plaintext,
+        aad: &aad,
+    };
+    let body = Aes256Gcm::new(&k.into())
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .expect("plaintext too long for AES-GCM");
+
+    blob.extend_from_slice(&body);
+    blob
+}
+
+/// Decrypt a blob produced by `hybrid_encrypt` using a user secret key.
+///
+/// Fails if the framing is invalid, or if the blob was not encrypted for the identity
+/// of the user secret key or has been tampered with.
+/// A mismatch in the associated data `aad` also results in `HybridError::Decryption`.
+pub fn hybrid_decrypt(
+    usk: &UserSecretKey,
+    blob: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>, HybridError> {
+    if blob.len() < HEADERSIZE {
+        return Err(HybridError::Truncated);
+    }
+    if blob[0] != VERSION {
+        return Err(HybridError::UnsupportedVersion(blob[0]));
+    }
+
+    let (header, body) = blob.split_at(HEADERSIZE);
+    let c = CipherText::try_from_bytes(array_ref![header, 1, CIPHERTEXT_BYTES])
+        .map_err(HybridError::CipherText)?;
+    let nonce = Nonce::from_slice(&header[1 + CIPHERTEXT_BYTES..]);
+    let k = decapsulate(usk, &c);
+
+    let aad = associated_data(header, aad);
+    let payload = Payload {
+        msg: body,
+        aad: &aad,
+    };
+    Aes256Gcm::new(&k.into())
+        .decrypt(nonce, payload)
+        .map_err(|_| HybridError::Decryption)
+}
+
+/// Encrypts a stream of arbitrary length for an identity in segments, see the module documentation.
+pub struct HybridEncryptor {
+    cipher: Aes256Gcm,
+    counter: u32,
+    buffer: Vec<u8>,
+}
+
+/// Decrypts a stream produced by `HybridEncryptor` using a user secret key.
+pub struct HybridDecryptor {
+    cipher: Aes256Gcm,
+    counter: u32,
+    buffer: Vec<u8>,
+}
+
+impl HybridEncryptor {
+    /// Start encrypting a stream for an identity using the PKG public key.
+    ///
+    /// The returned IBE ciphertext must be passed to `HybridDecryptor::new`.
+    pub fn new<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> (CipherText, HybridEncryptor) {
+        let (c, k) = encapsulate(pk, v, rng);
+        let enc = HybridEncryptor {
+            cipher: stream_cipher(&k),
+            counter: 0,
+            buffer: Vec::with_capacity(SEGMENT_SIZE),
+        };
+
+        (c, enc)
+    }
+
+    /// Encrypt the next part of the plaintext, returning the segments completed by it.
+    ///
+    /// The plaintext can be passed in parts of any size; it is buffered until a segment is full.
+    ///
+    /// # Panics
+    /// If the stream exceeds 2^32 segments, which is 256 TiB.
+    pub fn update(&mut self, plaintext: &[u8]) -> Vec<u8> {
+        self.buffer.extend_from_slice(plaintext);
+
+        // A full segment is only encrypted once more plaintext follows, as it may be the last.
+        let n = self.buffer.len().saturating_sub(1) / SEGMENT_SIZE;
+        let mut res = Vec::with_capacity(n * (SEGMENT_SIZE + TAGSIZE));
+        for segment in self.buffer[..n * SEGMENT_SIZE].chunks_exact(SEGMENT_SIZE) {
+            res.extend_from_slice(&seal(&self.cipher, &mut self.counter, segment, false));
+        }
+        self.buffer.drain(..n * SEGMENT_SIZE);
+
+        res
+    }
+
+    /// Encrypt the remaining plaintext as the last segment.
+    pub fn finalize(mut self) -> Vec<u8> {
+        seal(&self.cipher, &mut self.counter, &self.buffer, true)
+    }
+}
+
+impl HybridDecryptor {
+    /// Start decrypting a stream, given the IBE ciphertext returned by `HybridEncryptor::new`.
+    pub fn new(usk: &UserSecretKey, c: &CipherText) -> HybridDecryptor {
+        HybridDecryptor {
+            cipher: stream_cipher(&decapsulate(usk, c)),
+            counter: 0,
+            buffer: Vec::with_capacity(SEGMENT_SIZE + TAGSIZE),
+        }
+    }
+
+    /// Decrypt the next part of the stream, returning the plaintext of the segments completed by it.
+    ///
+    /// The stream can be passed in parts of any size. Fails if a segment has been tampered with,
+    /// reordered, or was not encrypted for the identity of the user secret key.
+    pub fn update(&mut self, data: &[u8]) -> Result<Vec<u8>, HybridError> {
+        self.buffer.extend_from_slice(data);
+
+        let n = self.buffer.len().saturating_sub(1) / (SEGMENT_SIZE + TAGSIZE);
+        let mut res = Vec::with_capacity(n * SEGMENT_SIZE);
+        for segment in
+            self.buffer[..n * (SEGMENT_SIZE + TAGSIZE)].chunks_exact(SEGMENT_SIZE + TAGSIZE)
+        {
+            res.extend_from_slice(&open(&self.cipher, &mut self.counter, segment, false)?);
+        }
+        self.buffer.drain(..n * (SEGMENT_SIZE + TAGSIZE));
+
+        Ok(res)
+    }
+
+    /// Decrypt the last segment, returning its plaintext.
+    ///
+    /// Fails if the stream has been truncated, in addition to the failures of `update`.
+    pub fn finalize(mut self) -> Result<Vec<u8>, HybridError> {
+        if self.buffer.len() < TAGSIZE {
+            return Err(HybridError::Truncated);
+        }
+        open(&self.cipher, &mut self.counter, &self.buffer, true)
+    }
+}
+
+fn stream_cipher(k: &[u8; 32]) -> Aes256Gcm {
+    Aes256Gcm::new(&sha3_256_dst(STREAM_KEY_DST, &[k]).into())
+}
+
+fn stream_nonce(counter: &mut u32, last: bool) -> [u8; NONCESIZE] {
+    let mut nonce = [0u8; NONCESIZE];
+    nonce[7..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = last as u8;
+
+    *counter = counter.checked_add(1).expect("too many segments");
+    nonce
+}
+
+fn seal(cipher: &Aes256Gcm, counter: &mut u32, segment: &[u8], last: bool) -> Vec<u8> {
+    let nonce = stream_nonce(counter, last);
+    cipher
+        .encrypt(Nonce::from_slice(&nonce), segment)
+        .expect("segment too long for AES-GCM")
+}
+
+fn open(
+    cipher: &Aes256Gcm,
+    counter: &mut u32,
+    segment: &[u8],
+    last: bool,
+) -> Result<Vec<u8>, HybridError> {
+    let nonce = stream_nonce(counter, last);
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), segment)
+        .map_err(|_| HybridError::Decryption)
+}
+
+fn associated_data(header: &[u8], aad: &[u8]) -> Vec<u8> {
+    let mut res = Vec::with_capacity(HEADERSIZE + aad.len());
+    res.extend_from_slice(&header[..HEADERSIZE]);
+    res.extend_from_slice(aad);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::waters::{extract_usk, setup};
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    #[test]
+    fn eq_hybrid_encrypt_decrypt() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_str(ID);
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let plaintext = b"Some arbitrary-length message that does not fit in a single Gt element.";
+        let blob = hybrid_encrypt(&pk, &kid, plaintext, b"", &mut rng);
+
+        assert_eq!(blob.len(), HEADERSIZE + plaintext.len() + 16);
+        assert_eq!(
+            hybrid_decrypt(&usk, &blob, b"").unwrap(),
+            plaintext.to_vec()
+        );
+
+        let other = extract_usk(
+            &pk,
+            &sk,
+            &Identity::derive_str("email:someone@else.nl"),
+            &mut rng,
+        );
+        assert_eq!(
+            hybrid_decrypt(&other, &blob, b""),
+            Err(HybridError::Decryption)
+        );
+
+        let mut tampered = blob.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            hybrid_decrypt(&usk, &tampered, b""),
+            Err(HybridError::Decryption)
+        );
+
+        let mut versioned = blob.clone();
+        versioned[0] = 2;
+        assert_eq!(
+            hybrid_decrypt(&usk, &versioned, b""),
+            Err(HybridError::UnsupportedVersion(2))
+        );
+        assert_eq!(
+            hybrid_decrypt(&usk, &blob[..HEADERSIZE - 1], b""),
+            Err(HybridError::Truncated)
+        );
+    }
+
+    #[test]
+    fn hybrid_associated_data() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_str(ID);
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let blob = hybrid_encrypt(&pk, &kid, b"message", b"channel 1", &mut rng);
+
+        assert_eq!(
+            hybrid_decrypt(&usk, &blob, b"channel 1").unwrap(),
+            b"message".to_vec()
+        );
+        assert_eq!(
+            hybrid_decrypt(&usk, &blob, b"channel 2"),
+            Err(HybridError::Decryption)
+        );
+        assert_eq!(
+            hybrid_decrypt(&usk, &blob, b""),
+            Err(HybridError::Decryption)
+        );
+    }
+
+    #[test]
+    fn eq_stream_encrypt_decrypt() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_str(ID);
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        for &len in &[0, 1000, SEGMENT_SIZE, 2 * SEGMENT_SIZE + 100] {
+            let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+
+            let (c, mut enc) = HybridEncryptor::new(&pk, &kid, &mut rng);
+            let mut stream = Vec::new();
+            for part in plaintext.chunks(5000) {
+                stream.extend_from_slice(&enc.update(part));
+            }
+            stream.extend_from_slice(&enc.finalize());
+            let segments = len / SEGMENT_SIZE + usize::from(len == 0 || len % SEGMENT_SIZE != 0);
+            assert_eq!(stream.len(), len + segments * TAGSIZE);
+
+            let mut dec = HybridDecryptor::new(&usk, &c);
+            let mut res = Vec::new();
+            for part in stream.chunks(7000) {
+                res.extend_from_slice(&dec.update(part).unwrap());
+            }
+            res.extend_from_slice(&dec.finalize().unwrap());
+            assert_eq!(res, plaintext);
+
+            // Dropping the last segment is detected.
+            if len > SEGMENT_SIZE {
+                let mut dec = HybridDecryptor::new(&usk, &c);
+                let full = SEGMENT_SIZE + TAGSIZE;
+                dec.update(&stream[..(stream.len() - 1) / full * full])
+                    .unwrap();
+                assert_eq!(dec.finalize(), Err(HybridError::Decryption));
+            }
+        }
+    }
+}
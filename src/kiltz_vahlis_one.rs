@@ -11,7 +11,7 @@
 use crate::util::*;
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 use irmaseal_curve::{G1Affine, G1Projective, G2Affine, Gt, Scalar};
-use rand::Rng;
+use rand::{CryptoRng, RngCore};
 use subtle::{Choice, ConditionallySelectable, CtOption};
 
 const K: usize = 256;
@@ -67,7 +67,7 @@ pub struct CipherText {
 pub struct SymmetricKey(Gt);
 
 /// Generate a keypair used by the Private Key Generator (PKG).
-pub fn setup<R: Rng>(rng: &mut R) -> (PublicKey, SecretKey) {
+pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
     let g: G2Affine = rand_g2(rng).into();
 
     let alpha: G1Affine = rand_g1(rng).into();
@@ -100,7 +100,7 @@ fn hash_g2_to_scalar(x: G2Affine) -> Scalar {
 }
 
 /// Extract an user secret key for a given identity.
-pub fn extract_usk<R: Rng>(
+pub fn extract_usk<R: RngCore + CryptoRng>(
     pk: &PublicKey,
     sk: &SecretKey,
     v: &Identity,
@@ -116,7 +116,11 @@ pub fn extract_usk<R: Rng>(
 }
 
 /// Generate a symmetric key and corresponding CipherText for that key.
-pub fn encrypt<R: Rng>(pk: &PublicKey, v: &Identity, rng: &mut R) -> (CipherText, SymmetricKey) {
+pub fn encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    rng: &mut R,
+) -> (CipherText, SymmetricKey) {
     let r = rand_scalar(rng);
 
     let c1 = (pk.g * r).into();
@@ -154,11 +158,7 @@ impl Identity {
 
 impl Clone for Identity {
     fn clone(&self) -> Self {
-        let mut res = [u8::default(); N_BYTE_LEN];
-        for (src, dst) in self.0.iter().zip(res.as_mut().iter_mut()) {
-            *dst = *src;
-        }
-        Identity(res)
+        *self
     }
 }
 
@@ -175,10 +175,11 @@ impl SymmetricKey {
 }
 
 impl HashParameters {
+    #[allow(clippy::wrong_self_convention)]
     pub fn to_bytes(&self) -> [u8; HASH_PARAMETER_SIZE] {
         let mut res = [0u8; HASH_PARAMETER_SIZE];
-        for i in 0..N {
-            *array_mut_ref![&mut res, i * 48, 48] = self.0[i].to_compressed();
+        for (i, p) in self.0.iter().enumerate() {
+            *array_mut_ref![&mut res, i * 48, 48] = p.to_compressed();
         }
         res
     }
@@ -186,10 +187,10 @@ impl HashParameters {
     pub fn from_bytes(bytes: &[u8; HASH_PARAMETER_SIZE]) -> CtOption<Self> {
         let mut res = [G1Affine::default(); N];
         let mut is_some = Choice::from(1u8);
-        for i in 0..N {
+        for (i, ri) in res.iter_mut().enumerate() {
             is_some &= G1Affine::from_compressed(array_ref![bytes, i * 48, 48])
                 .map(|s| {
-                    res[i] = s;
+                    *ri = s;
                 })
                 .is_some();
         }
@@ -201,7 +202,7 @@ impl ConditionallySelectable for HashParameters {
     fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
         let mut res = [G1Affine::default(); N];
         for (i, (ai, bi)) in a.0.iter().zip(b.0.iter()).enumerate() {
-            res[i] = G1Affine::conditional_select(&ai, &bi, choice);
+            res[i] = G1Affine::conditional_select(ai, bi, choice);
         }
         HashParameters(res)
     }
@@ -215,11 +216,7 @@ impl PartialEq for HashParameters {
 
 impl Clone for HashParameters {
     fn clone(&self) -> Self {
-        let mut res = [G1Affine::default(); N];
-        for (src, dst) in self.0.iter().zip(res.as_mut().iter_mut()) {
-            *dst = *src;
-        }
-        Self(res)
+        *self
     }
 }
 
@@ -315,7 +312,7 @@ impl CipherText {
 mod tests {
     use super::*;
 
-    const ID: &'static str = "email:w.geraedts@sarif.nl";
+    const ID: &str = "email:w.geraedts@sarif.nl";
 
     #[allow(dead_code)]
     struct DefaultSubResults {
@@ -0,0 +1,485 @@
+//! Identity Based Encryption Lewko-Waters scheme on the [BLS12-381 pairing-friendly elliptic curve](https://github.com/zkcrypto/bls12_381).
+//!  * From: "[New Techniques for Dual System Encryption and Fully Secure HIBE with Short Ciphertexts](https://link.springer.com/chapter/10.1007/978-3-642-11799-2_27)"
+//!  * Published in: TCC, 2010
+//!  * Translated to prime order groups using dual pairing vector spaces as in "[Tools for Simulating Features of Composite Order Bilinear Groups in the Prime Order Setting](https://link.springer.com/chapter/10.1007/978-3-642-29011-4_20)", EUROCRYPT, 2012
+//!  * Adapted to asymmetric pairings
+//!
+//! Uses [SHA3-512](https://crates.io/crates/tiny-keccak) for hashing to identities.
+//!
+//! Provides adaptive-identity security in the standard model using a dual system proof,
+//! under the decisional linear assumption. Unlike Waters, the public key has a constant size,
+//! at the cost of ciphertexts and user secret keys of six group elements each.
+//!
+//! The secret key contains the exponents of the dual basis vectors rather than the
+//! group elements, so extraction only requires six scalar multiplications.
+//!
+//! The structure of the byte serialisation of the various datastructures is not guaranteed
+//! to remain constant between releases of this library.
+//! All operations in this library are implemented to run in constant time.
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, CtOption};
+
+use crate::util::*;
+use irmaseal_curve::{G1Affine, G2Affine, Gt, Scalar};
+
+/// Dimension of the dual pairing vector spaces.
+const DIM: usize = 6;
+
+const PUBLICKEYSIZE: usize = 4 * DIM * 48 + 288;
+const SECRETKEYSIZE: usize = 5 * DIM * 32;
+const USERSECRETKEYSIZE: usize = DIM * 96;
+const CIPHERTEXTSIZE: usize = 288 + DIM * 48;
+
+const IDENTITY_DST: &[u8] = b"ibe-lewko-waters-identity";
+
+/// Public key parameters generated by the PKG used to encrypt messages.
+///
+/// Contains the basis vectors `d1` up to `d4` in the exponent of the generator of G1,
+/// and `e(g1, g2)^(alpha * theta * psi)`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PublicKey {
+    d: [[G1Affine; DIM]; 4],
+    y: Gt,
+}
+
+/// Secret key parameters generated by the PKG used to extract user secret keys.
+///
+/// Contains the vectors `alpha * theta * d1*`, `theta * d1*`, `theta * d2*`, `sigma * d3*`
+/// and `sigma * d4*` of the dual basis.
+/// The `Debug` output is redacted.
+#[derive(Clone, PartialEq)]
+pub struct SecretKey {
+    k: [[Scalar; DIM]; 5],
+}
+
+/// Vector of points on G2 that forms the user secret key.
+/// The `Debug` output is redacted.
+#[derive(Clone, PartialEq)]
+pub struct UserSecretKey {
+    k: [G2Affine; DIM],
+}
+
+/// Field element for an identity.
+///
+/// Effectively a hash of an identity, mapped to the curve field.
+/// Together with the public key parameters generated by the PKG forms the user public key.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Identity(Scalar);
+
+/// A point on the paired curve that can be encrypted and decrypted.
+///
+/// You can use the byte representation to derive an AES key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Message(Gt);
+
+/// Encrypted message. Can only be decrypted with an user secret key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CipherText {
+    c0: Gt,
+    c: [G1Affine; DIM],
+}
+
+/// The Lewko-Waters scheme, implementing the common `IbeScheme` interface.
+#[derive(Clone, Copy, Debug)]
+pub struct LewkoWaters;
+
+impl_ibe_scheme!(LewkoWaters);
+
+/// Generate a keypair used by the Private Key Generator (PKG).
+pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+    let (d, dstar) = dual_bases(rng);
+
+    let alpha = rand_scalar(rng);
+    let theta = rand_scalar(rng);
+    let sigma = rand_scalar(rng);
+
+    let g = G1Affine::generator();
+    let mut pk_d = [[G1Affine::identity(); DIM]; 4];
+    for (pi, di) in pk_d.iter_mut().zip(d.iter()) {
+        for (p, x) in pi.iter_mut().zip(di.iter()) {
+            *p = (g * x).into();
+        }
+    }
+
+    // As the bases are dual, d1 . d1* = psi.
+    let psi = dot(&d[0], &dstar[0]);
+    let y = irmaseal_curve::pairing(&g, &G2Affine::generator()) * (alpha * theta * psi);
+
+    let k = [
+        scale(&dstar[0], &(alpha * theta)),
+        scale(&dstar[0], &theta),
+        scale(&dstar[1], &theta),
+        scale(&dstar[2], &sigma),
+        scale(&dstar[3], &sigma),
+    ];
+
+    (PublicKey { d: pk_d, y }, SecretKey { k })
+}
+
+/// Extract an user secret key for a given identity.
+pub fn extract_usk<R: RngCore + CryptoRng>(
+    _pk: &PublicKey,
+    sk: &SecretKey,
+    v: &Identity,
+    rng: &mut R,
+) -> UserSecretKey {
+    let r1 = rand_scalar(rng);
+    let r2 = rand_scalar(rng);
+
+    // (alpha + r1 * id) * theta * d1* - r1 * theta * d2* + r2 * id * sigma * d3* - r2 * sigma * d4*
+    let coefficients = [Scalar::one(), r1 * v.0, -r1, r2 * v.0, -r2];
+
+    let g = G2Affine::generator();
+    let mut k = [G2Affine::identity(); DIM];
+    for (j, kj) in k.iter_mut().enumerate() {
+        let e = coefficients
+            .iter()
+            .zip(sk.k.iter())
+            .fold(Scalar::zero(), |acc, (a, b)| acc + a * b[j]);
+        *kj = (g * e).into();
+    }
+
+    UserSecretKey { k }
+}
+
+/// Encrypt a message using the PKG public key and an identity.
+pub fn encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let s1 = rand_scalar(rng);
+    let s2 = rand_scalar(rng);
+
+    // s1 * d1 + s1 * id * d2 + s2 * d3 + s2 * id * d4
+    let coefficients = [s1, s1 * v.0, s2, s2 * v.0];
+
+    let mut c = [G1Affine::identity(); DIM];
+    for (j, cj) in c.iter_mut().enumerate() {
+        let sum = coefficients
+            .iter()
+            .zip(pk.d.iter())
+            .fold(irmaseal_curve::G1Projective::identity(), |acc, (a, b)| {
+                acc + b[j] * a
+            });
+        *cj = sum.into();
+    }
+
+    let c0 = pk.y * s1 + m.0;
+
+    CipherText { c0, c }
+}
+
+/// Decrypt ciphertext to a message using a user secret key.
+pub fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
+    let mut pairs = [(G1Affine::identity(), G2Affine::identity()); DIM];
+    for (p, (cj, kj)) in pairs.iter_mut().zip(c.c.iter().zip(usk.k.iter())) {
+        *p = (*cj, *kj);
+    }
+
+    Message(c.c0 - pairing_product(&pairs))
+}
+
+/// Sample random dual orthonormal bases `(d, d*)`, such that `d_i . d_j* = 0` for `i != j`,
+/// and `d_i . d_i* = psi` for a random `psi`.
+///
+/// Fails with negligible probability, if the random matrix `d` is not invertible
+/// without pivoting, in which case the bases are not dual.
+fn dual_bases<R: RngCore + CryptoRng>(rng: &mut R) -> ([[Scalar; DIM]; DIM], [[Scalar; DIM]; DIM]) {
+    let mut d = [[Scalar::zero(); DIM]; DIM];
+    for x in d.iter_mut().flat_map(|di| di.iter_mut()) {
+        *x = rand_scalar(rng);
+    }
+    let psi = rand_scalar(rng);
+
+    // Gauss-Jordan elimination of d, applying the same row operations to the identity.
+    let mut a = d;
+    let mut inv = [[Scalar::zero(); DIM]; DIM];
+    for (i, row) in inv.iter_mut().enumerate() {
+        row[i] = Scalar::one();
+    }
+    for i in 0..DIM {
+        let p = a[i][i].invert().unwrap_or(Scalar::zero());
+        a[i] = scale(&a[i], &p);
+        inv[i] = scale(&inv[i], &p);
+
+        for k in 0..DIM {
+            if k != i {
+                let f = a[k][i];
+                for j in 0..DIM {
+                    a[k][j] -= f * a[i][j];
+                    inv[k][j] -= f * inv[i][j];
+                }
+            }
+        }
+    }
+
+    // d* = psi * (d^-1)^T
+    let mut dstar = [[Scalar::zero(); DIM]; DIM];
+    for (i, row) in dstar.iter_mut().enumerate() {
+        for (j, x) in row.iter_mut().enumerate() {
+            *x = psi * inv[j][i];
+        }
+    }
+
+    (d, dstar)
+}
+
+fn dot(a: &[Scalar; DIM], b: &[Scalar; DIM]) -> Scalar {
+    a.iter()
+        .zip(b.iter())
+        .fold(Scalar::zero(), |acc, (x, y)| acc + x * y)
+}
+
+fn scale(a: &[Scalar; DIM], s: &Scalar) -> [Scalar; DIM] {
+    let mut res = *a;
+    for x in res.iter_mut() {
+        *x *= s;
+    }
+    res
+}
+
+impl Identity {
+    /// Hash a byte slice to an Identity field element, which acts as a user public key.
+    /// Uses a domain-separated sha3-512 internally.
+    pub fn derive(b: &[u8]) -> Identity {
+        Identity(Scalar::from_bytes_wide(&sha3_512_dst(IDENTITY_DST, &[b])))
+    }
+
+    /// Hash a string slice to an Identity field element.
+    /// Directly converts characters to UTF-8 byte representation.
+    pub fn derive_str(s: &str) -> Identity {
+        Self::derive(s.as_bytes())
+    }
+}
+
+impl PublicKey {
+    pub fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
+        let mut res = [0u8; PUBLICKEYSIZE];
+        let (d, y) = mut_array_refs![&mut res, 4 * DIM * 48, 288];
+        for (i, p) in self.d.iter().flat_map(|di| di.iter()).enumerate() {
+            *array_mut_ref![d, i * 48, 48] = p.to_compressed();
+        }
+        *y = self.y.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; PUBLICKEYSIZE]) -> CtOption<Self> {
+        let (d_bytes, y) = array_refs![bytes, 4 * DIM * 48, 288];
+
+        let mut d = [[G1Affine::identity(); DIM]; 4];
+        let mut is_some = Choice::from(1u8);
+        for (i, p) in d.iter_mut().flat_map(|di| di.iter_mut()).enumerate() {
+            is_some &= G1Affine::from_compressed(array_ref![d_bytes, i * 48, 48])
+                .map(|x| *p = x)
+                .is_some();
+        }
+
+        Gt::from_compressed(y).and_then(|y| CtOption::new(PublicKey { d, y }, is_some))
+    }
+}
+
+impl SecretKey {
+    pub fn to_bytes(&self) -> [u8; SECRETKEYSIZE] {
+        let mut res = [0u8; SECRETKEYSIZE];
+        for (i, x) in self.k.iter().flat_map(|ki| ki.iter()).enumerate() {
+            *array_mut_ref![&mut res, i * 32, 32] = x.to_bytes();
+        }
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; SECRETKEYSIZE]) -> CtOption<Self> {
+        let mut k = [[Scalar::zero(); DIM]; 5];
+        let mut is_some = Choice::from(1u8);
+        for (i, x) in k.iter_mut().flat_map(|ki| ki.iter_mut()).enumerate() {
+            is_some &= Scalar::from_bytes(array_ref![bytes, i * 32, 32])
+                .map(|s| *x = s)
+                .is_some();
+        }
+
+        CtOption::new(SecretKey { k }, is_some)
+    }
+}
+
+impl UserSecretKey {
+    pub fn to_bytes(&self) -> [u8; USERSECRETKEYSIZE] {
+        let mut res = [0u8; USERSECRETKEYSIZE];
+        for (i, p) in self.k.iter().enumerate() {
+            *array_mut_ref![&mut res, i * 96, 96] = p.to_compressed();
+        }
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; USERSECRETKEYSIZE]) -> CtOption<Self> {
+        let mut k = [G2Affine::identity(); DIM];
+        let mut is_some = Choice::from(1u8);
+        for (i, p) in k.iter_mut().enumerate() {
+            is_some &= G2Affine::from_compressed(array_ref![bytes, i * 96, 96])
+                .map(|x| *p = x)
+                .is_some();
+        }
+
+        CtOption::new(UserSecretKey { k }, is_some)
+    }
+}
+
+impl Message {
+    /// Generate a random point on the paired curve.
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self(rand_gt(rng))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 288] {
+        self.0.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
+        Gt::from_compressed(bytes).map(Message)
+    }
+}
+
+impl CipherText {
+    pub fn to_bytes(&self) -> [u8; CIPHERTEXTSIZE] {
+        let mut res = [0u8; CIPHERTEXTSIZE];
+        let (c0, c) = mut_array_refs![&mut res, 288, DIM * 48];
+        *c0 = self.c0.to_compressed();
+        for (i, p) in self.c.iter().enumerate() {
+            *array_mut_ref![c, i * 48, 48] = p.to_compressed();
+        }
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; CIPHERTEXTSIZE]) -> CtOption<Self> {
+        let (c0, c_bytes) = array_refs![bytes, 288, DIM * 48];
+
+        let mut c = [G1Affine::identity(); DIM];
+        let mut is_some = Choice::from(1u8);
+        for (i, p) in c.iter_mut().enumerate() {
+            is_some &= G1Affine::from_compressed(array_ref![c_bytes, i * 48, 48])
+                .map(|x| *p = x)
+                .is_some();
+        }
+
+        Gt::from_compressed(c0).and_then(|c0| CtOption::new(CipherText { c0, c }, is_some))
+    }
+}
+
+impl core::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("SecretKey(<redacted>)")
+    }
+}
+
+impl core::fmt::Debug for UserSecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("UserSecretKey(<redacted>)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    #[allow(dead_code)]
+    struct DefaultSubResults {
+        kid: Identity,
+        m: Message,
+        pk: PublicKey,
+        sk: SecretKey,
+        usk: UserSecretKey,
+        c: CipherText,
+    }
+
+    fn perform_default() -> DefaultSubResults {
+        let mut rng = rand::thread_rng();
+
+        let id = ID.as_bytes();
+        let kid = Identity::derive(id);
+
+        let m = Message::generate(&mut rng);
+
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let c = encrypt(&pk, &kid, &m, &mut rng);
+
+        DefaultSubResults {
+            kid,
+            m,
+            pk,
+            sk,
+            usk,
+            c,
+        }
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+        let m2 = decrypt(&results.usk, &results.c);
+
+        assert_eq!(results.m, m2);
+
+        let other = extract_usk(
+            &results.pk,
+            &results.sk,
+            &Identity::derive_str("email:someone@else.nl"),
+            &mut rng,
+        );
+        assert_ne!(results.m, decrypt(&other, &results.c));
+    }
+
+    #[test]
+    fn debug_output() {
+        let results = perform_default();
+
+        assert_eq!(std::format!("{:?}", results.sk), "SecretKey(<redacted>)");
+        assert_eq!(
+            std::format!("{:?}", results.usk),
+            "UserSecretKey(<redacted>)"
+        );
+    }
+
+    #[test]
+    fn dual_orthonormal_bases() {
+        let (d, dstar) = dual_bases(&mut rand::thread_rng());
+        let psi = dot(&d[0], &dstar[0]);
+
+        assert_ne!(psi, Scalar::zero());
+        for (i, di) in d.iter().enumerate() {
+            for (j, dj) in dstar.iter().enumerate() {
+                let expected = if i == j { psi } else { Scalar::zero() };
+                assert_eq!(dot(di, dj), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn eq_serialize_deserialize() {
+        let result = perform_default();
+
+        assert_eq!(result.m, Message::from_bytes(&result.m.to_bytes()).unwrap());
+        assert_eq!(
+            result.pk,
+            PublicKey::from_bytes(&result.pk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.sk,
+            SecretKey::from_bytes(&result.sk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.usk,
+            UserSecretKey::from_bytes(&result.usk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.c,
+            CipherText::from_bytes(&result.c.to_bytes()).unwrap()
+        );
+    }
+}
@@ -4,15 +4,30 @@
 //! * Waters
 //! * Waters-Naccache
 //! * Kiltz-Vahlis IBE1
+//! * Boneh-Franklin FullIdent
+//! * Boneh-Boyen BB1
+//! * Boyen-Waters (anonymous)
+//! * Gentry (anonymous)
+//! * Lewko-Waters
+//!
+//! The `signatures` module provides signatures from the Waters scheme using the Naor transform.
+//!
+//! With the `aead` feature, the `hybrid` module encrypts arbitrary-length messages
+//! by combining the Waters KEM with AES-256-GCM.
+//!
+//! The `compat` module reads Waters public keys and ciphertexts persisted using previous format versions.
+//!
+//! With the `wasm` feature, the `wasm` module provides JavaScript bindings for the Waters scheme.
 //!
 //! ## How to use
 //! The following example is similar for all the schemes.
 //! Check the corresponding tests for concrete examples per scheme.
+//! Most schemes also implement the [`scheme::IbeScheme`] trait, such that they can be used interchangeably.
 //!
 //! ```
 //! use ibe::kiltz_vahlis_one::*;
 //!
-//! const ID: &'static str = "email:w.geraedts@sarif.nl";
+//! const ID: &str = "email:w.geraedts@sarif.nl";
 //! let mut rng = rand::thread_rng();
 //!
 //! // Hash the identity to a set of scalars.
@@ -35,12 +50,53 @@
 
 #![no_std]
 
-#[cfg(test)]
+#[cfg(any(test, feature = "std"))]
 #[macro_use]
 extern crate std;
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[macro_use]
 mod util;
 
+#[macro_use]
+pub mod scheme;
+
+pub mod compat;
+pub mod error;
+pub mod signatures;
+
+#[cfg(feature = "aead")]
+pub mod hybrid;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub mod boneh_boyen_one;
+pub mod boneh_franklin;
+pub mod boyen_waters;
+pub mod gentry;
 pub mod kiltz_vahlis_one;
+pub mod lewko_waters;
 pub mod waters;
 pub mod waters_naccache;
+
+/// The scalar field of the BLS12-381 curve, for building protocols around the schemes.
+pub use irmaseal_curve::Scalar;
+
+/// The groups of the BLS12-381 pairing, such that the components of keys and ciphertexts are nameable.
+pub use irmaseal_curve::{G1Affine, G2Affine, Gt};
+
+/// Generate a uniformly random scalar.
+///
+/// Draws 64 bytes from the RNG and reduces them modulo the group order,
+/// such that the bias is negligible.
+///
+/// ```
+/// let mut rng = rand::thread_rng();
+/// assert_ne!(ibe::random_scalar(&mut rng), ibe::random_scalar(&mut rng));
+/// ```
+pub fn random_scalar<R: rand::RngCore + rand::CryptoRng>(rng: &mut R) -> Scalar {
+    util::rand_scalar(rng)
+}
@@ -0,0 +1,160 @@
+//! Common interface to the schemes in this library, such that they can be used interchangeably.
+
+use rand::{CryptoRng, RngCore};
+
+/// An Identity Based Encryption scheme that encrypts messages to identities.
+///
+/// Implemented by marker types in the modules of the corresponding schemes,
+/// for example `waters::Waters`.
+pub trait IbeScheme {
+    /// Public key parameters generated by the PKG used to encrypt messages.
+    type PublicKey;
+    /// Secret key parameters generated by the PKG used to extract user secret keys.
+    type SecretKey;
+    /// Secret key of a user for a single identity.
+    type UserSecretKey;
+    /// Identity that messages are encrypted for.
+    type Identity;
+    /// Message that can be encrypted and decrypted.
+    type Message;
+    /// Encrypted message.
+    type CipherText;
+
+    /// Generate a keypair used by the Private Key Generator (PKG).
+    fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (Self::PublicKey, Self::SecretKey);
+
+    /// Extract an user secret key for a given identity.
+    fn extract_usk<R: RngCore + CryptoRng>(
+        pk: &Self::PublicKey,
+        sk: &Self::SecretKey,
+        v: &Self::Identity,
+        rng: &mut R,
+    ) -> Self::UserSecretKey;
+
+    /// Generate a random message.
+    fn generate_message<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Message;
+
+    /// Encrypt a message using the PKG public key and an identity.
+    fn encrypt<R: RngCore + CryptoRng>(
+        pk: &Self::PublicKey,
+        v: &Self::Identity,
+        m: &Self::Message,
+        rng: &mut R,
+    ) -> Self::CipherText;
+
+    /// Decrypt ciphertext to a message using a user secret key.
+    fn decrypt(usk: &Self::UserSecretKey, c: &Self::CipherText) -> Self::Message;
+}
+
+/// Byte access to the message of a scheme, such that code deriving symmetric keys from
+/// messages, like hybrid encryption, can be written once for any scheme.
+///
+/// The messages of all schemes are elements of the paired group, but of distinct types.
+/// Currently implemented for `waters::Message`.
+pub trait MessageBytes {
+    /// The byte representation of a message.
+    type Bytes: AsRef<[u8]>;
+
+    /// The compressed representation of the message.
+    ///
+    /// This is not uniformly random and must not be used as a key directly.
+    fn to_bytes(&self) -> Self::Bytes;
+
+    /// Derive a 32-byte symmetric key, such as an AES-256 key, from this message.
+    fn into_aes_key(self) -> [u8; 32];
+}
+
+/// Implement `IbeScheme` for a marker type by delegating to the free functions of a scheme module.
+macro_rules! impl_ibe_scheme {
+    ($scheme:ident) => {
+        impl_ibe_scheme!($scheme, decrypt);
+    };
+    ($scheme:ident, $decrypt:ident) => {
+        impl crate::scheme::IbeScheme for $scheme {
+            type PublicKey = PublicKey;
+            type SecretKey = SecretKey;
+            type UserSecretKey = UserSecretKey;
+            type Identity = Identity;
+            type Message = Message;
+            type CipherText = CipherText;
+
+            fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+                setup(rng)
+            }
+
+            fn extract_usk<R: RngCore + CryptoRng>(
+                pk: &PublicKey,
+                sk: &SecretKey,
+                v: &Identity,
+                rng: &mut R,
+            ) -> UserSecretKey {
+                extract_usk(pk, sk, v, rng)
+            }
+
+            fn generate_message<R: RngCore + CryptoRng>(rng: &mut R) -> Message {
+                Message::generate(rng)
+            }
+
+            fn encrypt<R: RngCore + CryptoRng>(
+                pk: &PublicKey,
+                v: &Identity,
+                m: &Message,
+                rng: &mut R,
+            ) -> CipherText {
+                encrypt(pk, v, m, rng)
+            }
+
+            fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
+                $decrypt(usk, c)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        boneh_boyen_one, boneh_franklin, boyen_waters, gentry, lewko_waters, waters,
+        waters_naccache,
+    };
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    fn roundtrip<S: IbeScheme>(v: &S::Identity)
+    where
+        S::Message: PartialEq + core::fmt::Debug,
+    {
+        let mut rng = rand::thread_rng();
+
+        let (pk, sk) = S::setup(&mut rng);
+        let usk = S::extract_usk(&pk, &sk, v, &mut rng);
+
+        let m = S::generate_message(&mut rng);
+        let c = S::encrypt(&pk, v, &m, &mut rng);
+
+        assert_eq!(m, S::decrypt(&usk, &c));
+    }
+
+    fn aes_key<M: MessageBytes + Copy>(m: &M) -> ([u8; 32], usize) {
+        (m.into_aes_key(), m.to_bytes().as_ref().len())
+    }
+
+    #[test]
+    fn message_bytes_generic() {
+        let m = waters::Message::generate(&mut rand::thread_rng());
+
+        assert_eq!(aes_key(&m), (m.into_aes_key(), waters::MESSAGE_BYTES));
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt_generic() {
+        roundtrip::<waters::Waters>(&waters::Identity::derive_str(ID));
+        roundtrip::<waters_naccache::WatersNaccache>(&waters_naccache::Identity::derive_str(ID));
+        roundtrip::<boneh_boyen_one::BonehBoyenOne>(&boneh_boyen_one::Identity::derive_str(ID));
+        roundtrip::<boyen_waters::BoyenWaters>(&boyen_waters::Identity::derive_str(ID));
+        roundtrip::<lewko_waters::LewkoWaters>(&lewko_waters::Identity::derive_str(ID));
+        roundtrip::<boneh_franklin::BonehFranklin>(&boneh_franklin::Identity::derive_str(ID));
+        roundtrip::<gentry::Gentry>(&gentry::Identity::derive_str(ID));
+    }
+}
@@ -0,0 +1,328 @@
+//! Signatures from the Waters IBE scheme, using the transform attributed to Naor.
+//!
+//! The PKG acts as the signer: a signature on a message is the user secret key
+//! for the identity derived from that message, and verification checks the validity
+//! of that user secret key using the public key.
+//! Signatures and encryption can therefore share the same PKG keypair.
+//!
+//! Many signatures under the same public key can be verified at once using `verify_batch`.
+//!
+//! Messages are derived to identities in a separate domain from `Identity::derive`,
+//! such that a signature on a message is never a usable user secret key for an identity.
+//!
+//! `signcrypt` combines these signatures with encryption for sender authenticity:
+//! a sender holding its own PKG keypair encrypts for a recipient, and signs the ciphertext
+//! together with the message and its own public key. The signature is encrypted along with
+//! the message, so only the sender can have produced it.
+
+use arrayref::{array_refs, mut_array_refs};
+use rand::{CryptoRng, RngCore};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::error::SigncryptionError;
+#[cfg(feature = "alloc")]
+use crate::waters::are_valid_usks;
+use crate::waters::{
+    decrypt, encrypt, extract_usk, is_valid_usk, CipherText, Identity, Message, PublicKey,
+    SecretKey, UserSecretKey, CIPHERTEXT_BYTES, HASH_BYTE_LEN, MESSAGE_BYTES,
+    USER_SECRET_KEY_BYTES,
+};
+
+/// Domain for deriving identities from signed messages.
+const SIGNATURE_DOMAIN: &[u8] = b"ibe-waters-signature";
+
+/// Context prefixed to the signed data of signcrypted ciphertexts.
+const SIGNCRYPTION_CONTEXT: &[u8] = b"ibe-waters-signcryption";
+
+/// Context for deriving the mask of the signature from the message.
+const SIGNCRYPTION_MASK_INFO: &[u8] = b"ibe-waters-signcryption-mask";
+
+const SIGNCRYPTION_CONTEXT_BYTES: usize = SIGNCRYPTION_CONTEXT.len();
+
+const SIGNCRYPTION_PAYLOAD_BYTES: usize =
+    SIGNCRYPTION_CONTEXT_BYTES + 32 + HASH_BYTE_LEN + CIPHERTEXT_BYTES + MESSAGE_BYTES;
+
+/// Length in bytes of a serialized `SigncryptedCipherText`.
+pub const SIGNCRYPTED_CIPHERTEXT_BYTES: usize = CIPHERTEXT_BYTES + USER_SECRET_KEY_BYTES;
+
+/// A signature on a message, which is a user secret key for the message identity.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Signature(UserSecretKey);
+
+fn message_identity(msg: &[u8]) -> Identity {
+    Identity::derive_with_domain(SIGNATURE_DOMAIN, msg)
+}
+
+/// Sign a message using the PKG keypair.
+pub fn sign<R: RngCore + CryptoRng>(
+    sk: &SecretKey,
+    pk: &PublicKey,
+    msg: &[u8],
+    rng: &mut R,
+) -> Signature {
+    Signature(extract_usk(pk, sk, &message_identity(msg), rng))
+}
+
+/// Verify a signature on a message using the PKG public key.
+pub fn verify(pk: &PublicKey, msg: &[u8], sig: &Signature) -> bool {
+    is_valid_usk(pk, &message_identity(msg), &sig.0).into()
+}
+
+/// Verify many signatures on their messages using the same PKG public key.
+///
+/// Checks a random linear combination of the verification equations in a single multi-pairing,
+/// which is several times faster than calling `verify` for each signature.
+/// Returns `false` if any signature is invalid, or if the numbers of messages and signatures differ,
+/// without telling which signature failed; fall back to `verify` to find out.
+#[cfg(feature = "alloc")]
+pub fn verify_batch<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    msgs: &[&[u8]],
+    sigs: &[Signature],
+    rng: &mut R,
+) -> bool {
+    if msgs.len() != sigs.len() {
+        return false;
+    }
+
+    let items: Vec<(Identity, &UserSecretKey)> = msgs
+        .iter()
+        .zip(sigs.iter())
+        .map(|(msg, sig)| (message_identity(msg), &sig.0))
+        .collect();
+
+    are_valid_usks(pk, &items, rng).into()
+}
+
+/// Ciphertext for a recipient identity, signed by the sender.
+///
+/// Serialized as the ciphertext followed by the encrypted signature, taking 576 bytes.
+/// The signature covers the context `b"ibe-waters-signcryption"`, the fingerprint of the
+/// sender public key, the digest of the recipient identity, the serialized ciphertext and
+/// the message, so it can not be moved to another sender, recipient, ciphertext or message.
+/// It is masked with a key derived from the message, such that only the recipient can verify it,
+/// and replacing it requires knowing the message.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SigncryptedCipherText {
+    c: CipherText,
+    masked_sig: [u8; USER_SECRET_KEY_BYTES],
+}
+
+fn signcryption_payload(
+    sender_pk: &PublicKey,
+    v: &Identity,
+    c: &CipherText,
+    m: &Message,
+) -> [u8; SIGNCRYPTION_PAYLOAD_BYTES] {
+    let mut res = [0u8; SIGNCRYPTION_PAYLOAD_BYTES];
+    let (context, sender, id, ct, msg) = mut_array_refs![
+        &mut res,
+        SIGNCRYPTION_CONTEXT_BYTES,
+        32,
+        HASH_BYTE_LEN,
+        CIPHERTEXT_BYTES,
+        MESSAGE_BYTES
+    ];
+    context.copy_from_slice(SIGNCRYPTION_CONTEXT);
+    *sender = sender_pk.fingerprint();
+    *id = v.to_hash_bytes();
+    *ct = c.to_bytes();
+    *msg = m.to_bytes();
+    res
+}
+
+/// XOR the serialized signature with a mask derived from the message, which is its own inverse.
+fn mask_signature(m: &Message, sig: &[u8; USER_SECRET_KEY_BYTES]) -> [u8; USER_SECRET_KEY_BYTES] {
+    let mut res: [u8; USER_SECRET_KEY_BYTES] = m.derive_key(SIGNCRYPTION_MASK_INFO);
+    for (r, s) in res.iter_mut().zip(sig.iter()) {
+        *r ^= s;
+    }
+    res
+}
+
+/// Encrypt a message for a recipient identity, and sign the ciphertext using the PKG keypair of the sender.
+///
+/// The sender is identified by its public key, which the recipient must obtain authentically.
+/// The signature binds the sender to the message and is encrypted along with it,
+/// so others can not replace it to claim to have sent the ciphertext.
+pub fn signcrypt<R: RngCore + CryptoRng>(
+    sender_sk: &SecretKey,
+    sender_pk: &PublicKey,
+    recipient_pk: &PublicKey,
+    recipient_id: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> SigncryptedCipherText {
+    let c = encrypt(recipient_pk, recipient_id, m, rng);
+    let sig = sign(
+        sender_sk,
+        sender_pk,
+        &signcryption_payload(sender_pk, recipient_id, &c, m),
+        rng,
+    );
+    let masked_sig = mask_signature(m, &sig.to_bytes());
+
+    SigncryptedCipherText { c, masked_sig }
+}
+
+/// Decrypt a signcrypted ciphertext using the user secret key for the recipient identity,
+/// and verify the signature of the sender on it.
+///
+/// Fails if the signature was not made by the sender for this recipient identity,
+/// ciphertext and message.
+pub fn unsigncrypt(
+    sender_pk: &PublicKey,
+    recipient_usk: &UserSecretKey,
+    recipient_id: &Identity,
+    sct: &SigncryptedCipherText,
+) -> Result<Message, SigncryptionError> {
+    let m = decrypt(recipient_usk, &sct.c);
+    let sig = Signature::from_bytes(&mask_signature(&m, &sct.masked_sig))
+        .ok_or(SigncryptionError::Signature)?;
+
+    if !verify(
+        sender_pk,
+        &signcryption_payload(sender_pk, recipient_id, &sct.c, &m),
+        &sig,
+    ) {
+        return Err(SigncryptionError::Signature);
+    }
+
+    Ok(m)
+}
+
+impl SigncryptedCipherText {
+    pub fn to_bytes(&self) -> [u8; SIGNCRYPTED_CIPHERTEXT_BYTES] {
+        let mut res = [0u8; SIGNCRYPTED_CIPHERTEXT_BYTES];
+        let (c, sig) = mut_array_refs![&mut res, CIPHERTEXT_BYTES, USER_SECRET_KEY_BYTES];
+        *c = self.c.to_bytes();
+        *sig = self.masked_sig;
+        res
+    }
+
+    /// Deserialize a signcrypted ciphertext.
+    ///
+    /// The encrypted signature is only checked by `unsigncrypt`.
+    /// As signcrypted ciphertexts are public, this function does not run in constant time.
+    pub fn from_bytes(bytes: &[u8; SIGNCRYPTED_CIPHERTEXT_BYTES]) -> Option<Self> {
+        let (c, masked_sig) = array_refs![bytes, CIPHERTEXT_BYTES, USER_SECRET_KEY_BYTES];
+
+        let c = Option::from(CipherText::from_bytes(c))?;
+        Some(SigncryptedCipherText {
+            c,
+            masked_sig: *masked_sig,
+        })
+    }
+}
+
+impl Signature {
+    pub fn to_bytes(&self) -> [u8; USER_SECRET_KEY_BYTES] {
+        self.0.to_bytes()
+    }
+
+    /// Deserialize a signature.
+    ///
+    /// As signatures are public, this function does not run in constant time.
+    pub fn from_bytes(bytes: &[u8; USER_SECRET_KEY_BYTES]) -> Option<Self> {
+        Option::from(UserSecretKey::from_bytes(bytes)).map(Signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::waters::setup;
+
+    #[test]
+    fn sign_verify() {
+        let mut rng = rand::thread_rng();
+        let (pk, sk) = setup(&mut rng);
+
+        let sig = sign(&sk, &pk, b"some message", &mut rng);
+
+        assert!(verify(&pk, b"some message", &sig));
+        assert!(!verify(&pk, b"another message", &sig));
+
+        let (pk2, _) = setup(&mut rng);
+        assert!(!verify(&pk2, b"some message", &sig));
+
+        let sig2 = Signature::from_bytes(&sig.to_bytes()).unwrap();
+        assert!(verify(&pk, b"some message", &sig2));
+    }
+
+    #[test]
+    fn signcrypt_unsigncrypt() {
+        let mut rng = rand::thread_rng();
+        let (sender_pk, sender_sk) = setup(&mut rng);
+        let (pk, sk) = setup(&mut rng);
+
+        let alice = Identity::derive_str("email:alice@sarif.nl");
+        let usk = extract_usk(&pk, &sk, &alice, &mut rng);
+        let m = Message::generate(&mut rng);
+
+        let sct = signcrypt(&sender_sk, &sender_pk, &pk, &alice, &m, &mut rng);
+        let sct = SigncryptedCipherText::from_bytes(&sct.to_bytes()).unwrap();
+        assert_eq!(unsigncrypt(&sender_pk, &usk, &alice, &sct), Ok(m));
+
+        // Another sender, or another recipient identity, fails verification.
+        let (other_pk, _) = setup(&mut rng);
+        assert_eq!(
+            unsigncrypt(&other_pk, &usk, &alice, &sct),
+            Err(SigncryptionError::Signature)
+        );
+        let bob = Identity::derive_str("email:bob@sarif.nl");
+        assert_eq!(
+            unsigncrypt(&sender_pk, &usk, &bob, &sct),
+            Err(SigncryptionError::Signature)
+        );
+    }
+
+    #[test]
+    fn signcrypt_resign() {
+        let mut rng = rand::thread_rng();
+        let (sender_pk, sender_sk) = setup(&mut rng);
+        let (eve_pk, eve_sk) = setup(&mut rng);
+        let (pk, sk) = setup(&mut rng);
+
+        let alice = Identity::derive_str("email:alice@sarif.nl");
+        let usk = extract_usk(&pk, &sk, &alice, &mut rng);
+        let m = Message::generate(&mut rng);
+
+        let sct = signcrypt(&sender_sk, &sender_pk, &pk, &alice, &m, &mut rng);
+
+        // Without the message, another sender can not replace the signature by their own.
+        let mut forged = sct.clone();
+        forged.masked_sig = sign(&eve_sk, &eve_pk, &forged.c.to_bytes(), &mut rng).to_bytes();
+        assert_eq!(
+            unsigncrypt(&eve_pk, &usk, &alice, &forged),
+            Err(SigncryptionError::Signature)
+        );
+
+        // Tampering with the encrypted signature fails verification.
+        let mut tampered = sct.to_bytes();
+        tampered[SIGNCRYPTED_CIPHERTEXT_BYTES - 1] ^= 1;
+        let tampered = SigncryptedCipherText::from_bytes(&tampered).unwrap();
+        assert_eq!(
+            unsigncrypt(&sender_pk, &usk, &alice, &tampered),
+            Err(SigncryptionError::Signature)
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn verify_batch_one_bad() {
+        let mut rng = rand::thread_rng();
+        let (pk, sk) = setup(&mut rng);
+
+        let msgs: [&[u8]; 3] = [b"first", b"second", b"third"];
+        let mut sigs: Vec<Signature> = msgs.iter().map(|m| sign(&sk, &pk, m, &mut rng)).collect();
+        assert!(verify_batch(&pk, &msgs, &sigs, &mut rng));
+        assert!(!verify_batch(&pk, &msgs[..2], &sigs, &mut rng));
+
+        sigs[1] = sign(&sk, &pk, b"another message", &mut rng);
+        assert!(!verify_batch(&pk, &msgs, &sigs, &mut rng));
+        assert!(verify(&pk, msgs[0], &sigs[0]));
+    }
+}
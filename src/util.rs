@@ -1,10 +1,77 @@
+use crate::error::{DeserializeError, ParseError};
 use irmaseal_curve::{G1Affine, G1Projective, G2Affine, G2Projective, Gt, Scalar};
 
+/// Compute the product of the pairings of the given pairs.
+///
+/// Uses a single multi Miller loop with the `alloc` feature, and separate pairings otherwise.
+pub fn pairing_product(pairs: &[(G1Affine, G2Affine)]) -> Gt {
+    #[cfg(feature = "alloc")]
+    {
+        let prepared: alloc::vec::Vec<_> = pairs
+            .iter()
+            .map(|(p, q)| (p, irmaseal_curve::G2Prepared::from(*q)))
+            .collect();
+        let terms: alloc::vec::Vec<_> = prepared.iter().map(|(p, q)| (*p, q)).collect();
+        irmaseal_curve::multi_miller_loop(&terms).final_exponentiation()
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    pairs.iter().fold(Gt::identity(), |acc, (p, q)| {
+        acc + irmaseal_curve::pairing(p, q)
+    })
+}
+
+/// Check in constant time whether the product of the pairings of the given pairs is the identity.
+pub fn pairing_product_is_identity(pairs: &[(G1Affine, G2Affine)]) -> subtle::Choice {
+    use subtle::ConstantTimeEq;
+
+    pairing_product(pairs).ct_eq(&Gt::identity())
+}
+
 pub fn rand_scalar<R: ::rand::Rng>(rng: &mut R) -> Scalar {
     let mut buf = [0u8; 64];
     rng.fill_bytes(&mut buf);
 
-    Scalar::from_bytes_wide(&buf)
+    let res = Scalar::from_bytes_wide(&buf);
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut buf);
+    res
+}
+
+/// A secret scalar, such as the master secret or encryption randomness.
+///
+/// With the `zeroize` feature enabled, it is wiped from memory on drop.
+pub struct SecretScalar(Scalar);
+
+impl core::ops::Deref for SecretScalar {
+    type Target = Scalar;
+
+    fn deref(&self) -> &Scalar {
+        &self.0
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for SecretScalar {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.0);
+    }
+}
+
+/// Generate a random secret scalar, which is wiped from memory on drop with the `zeroize` feature.
+pub fn rand_secret_scalar<R: ::rand::Rng>(rng: &mut R) -> SecretScalar {
+    SecretScalar(rand_scalar(rng))
+}
+
+/// Hash the concatenated inputs to a secret scalar, using a domain-separated sha3-512.
+pub fn hash_secret_scalar(dst: &[u8], inputs: &[&[u8]]) -> SecretScalar {
+    #[cfg_attr(not(feature = "zeroize"), allow(unused_mut))]
+    let mut buf = sha3_512_dst(dst, inputs);
+
+    let res = Scalar::from_bytes_wide(&buf);
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut buf);
+    SecretScalar(res)
 }
 
 pub fn rand_g1<R: ::rand::Rng>(rng: &mut R) -> G1Projective {
@@ -28,10 +95,342 @@ pub fn rand_gt<R: ::rand::Rng>(rng: &mut R) -> Gt {
     generator * r
 }
 
+/// Hash the concatenated inputs with sha3-256, prefixed by a length-prefixed domain separation tag.
+pub fn sha3_256_dst(dst: &[u8], inputs: &[&[u8]]) -> [u8; 32] {
+    let mut res = [0u8; 32];
+    keccak_dst(tiny_keccak::Keccak::new_sha3_256(), dst, inputs, &mut res);
+    res
+}
+
+/// Hash the concatenated inputs with sha3-512, prefixed by a length-prefixed domain separation tag.
+pub fn sha3_512_dst(dst: &[u8], inputs: &[&[u8]]) -> [u8; 64] {
+    let mut res = [0u8; 64];
+    keccak_dst(tiny_keccak::Keccak::new_sha3_512(), dst, inputs, &mut res);
+    res
+}
+
+/// Hash the concatenated inputs with SHAKE256 to `N` bytes, prefixed by a length-prefixed
+/// domain separation tag.
+pub fn shake256_dst<const N: usize>(dst: &[u8], inputs: &[&[u8]]) -> [u8; N] {
+    let mut res = [0u8; N];
+    keccak_dst(tiny_keccak::Keccak::new_shake256(), dst, inputs, &mut res);
+    res
+}
+
+/// Absorb a domain separation tag, prefixed by its length as a big-endian u64.
+///
+/// All domain-separated hashes of this library start with this prefix.
+pub fn absorb_dst(h: &mut tiny_keccak::Keccak, dst: &[u8]) {
+    h.update(&(dst.len() as u64).to_be_bytes());
+    h.update(dst);
+}
+
+fn keccak_dst(mut h: tiny_keccak::Keccak, dst: &[u8], inputs: &[&[u8]], out: &mut [u8]) {
+    absorb_dst(&mut h, dst);
+    for input in inputs {
+        h.update(input);
+    }
+    h.finalize(out);
+}
+
+/// SHAKE256 of tiny-keccak, implementing the `digest` traits required by the expander of RFC 9380.
+#[derive(Clone)]
+struct Shake256(tiny_keccak::Keccak);
+
+struct Shake256Reader(tiny_keccak::XofReader);
+
+impl Default for Shake256 {
+    fn default() -> Self {
+        Shake256(tiny_keccak::Keccak::new_shake256())
+    }
+}
+
+impl digest::Update for Shake256 {
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        self.0.update(data.as_ref());
+    }
+}
+
+impl digest::ExtendableOutputDirty for Shake256 {
+    type Reader = Shake256Reader;
+
+    fn finalize_xof_dirty(&mut self) -> Shake256Reader {
+        Shake256Reader(core::mem::take(self).0.xof())
+    }
+}
+
+impl digest::XofReader for Shake256Reader {
+    fn read(&mut self, buffer: &mut [u8]) {
+        self.0.squeeze(buffer);
+    }
+}
+
+/// Hash a byte slice to a point on G1, using SHAKE256 as the expander.
+pub fn hash_to_g1(msg: &[u8], dst: &[u8]) -> G1Projective {
+    use irmaseal_curve::hash_to_curve::{ExpandMsgXof, HashToCurve};
+    <G1Projective as HashToCurve<ExpandMsgXof<Shake256>>>::hash_to_curve(msg, dst)
+}
+
+/// Hash a byte slice to a scalar, using `hash_to_field` of RFC 9380 with SHAKE256 as the expander.
+pub fn hash_to_scalar(msg: &[u8], dst: &[u8]) -> Scalar {
+    use irmaseal_curve::hash_to_curve::{ExpandMsgXof, HashToField};
+
+    let mut res = [Scalar::zero()];
+    Scalar::hash_to_field::<ExpandMsgXof<Shake256>>(msg, dst, &mut res);
+    res[0]
+}
+
 pub fn bits<'a>(slice: &'a [u8]) -> impl Iterator<Item = subtle::Choice> + 'a {
-    slice
-        .iter()
-        .rev()
-        .zip((0..8).rev())
-        .map(|(x, i)| subtle::Choice::from((*x >> i) & 1))
+    slice.iter().rev().flat_map(|x| {
+        (0..8)
+            .rev()
+            .map(move |i| subtle::Choice::from((*x >> i) & 1))
+    })
+}
+
+/// The modulus of the base field of BLS12-381, big-endian.
+const FIELD_MODULUS: [u8; 48] = [
+    0x1a, 0x01, 0x11, 0xea, 0x39, 0x7f, 0xe6, 0x9a, 0x4b, 0x1b, 0xa7, 0xb6, 0x43, 0x4b, 0xac, 0xd7,
+    0x64, 0x77, 0x4b, 0x84, 0xf3, 0x85, 0x12, 0xbf, 0x67, 0x30, 0xd2, 0xa0, 0xf6, 0xb0, 0xf6, 0x24,
+    0x1e, 0xab, 0xff, 0xfe, 0xb1, 0x53, 0xff, 0xff, 0xb9, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xaa, 0xab,
+];
+
+/// Determine why a compressed point on G1 or G2 was rejected, given whether it
+/// decompresses without the subgroup check.
+fn point_error(bytes: &[u8], on_curve: bool, field: &'static str) -> DeserializeError {
+    if on_curve {
+        return DeserializeError::WrongSubgroup(field);
+    }
+
+    let compressed = bytes[0] & 0x80 != 0;
+    let infinity = bytes[0] & 0x40 != 0;
+    let reduced = bytes.chunks_exact(48).enumerate().all(|(i, c)| {
+        let mut x = [0u8; 48];
+        x.copy_from_slice(c);
+        if i == 0 {
+            x[0] &= 0x1f;
+        }
+        x < FIELD_MODULUS
+    });
+
+    // The canonical point at infinity always decompresses, so any other encoding with
+    // the infinity flag set is not canonical.
+    if !compressed || infinity || !reduced {
+        DeserializeError::NonCanonical(field)
+    } else {
+        DeserializeError::OffCurve(field)
+    }
+}
+
+pub fn try_g1(bytes: &[u8; 48], field: &'static str) -> Result<G1Affine, DeserializeError> {
+    Option::from(G1Affine::from_compressed(bytes)).ok_or_else(|| {
+        let on_curve = G1Affine::from_compressed_unchecked(bytes).is_some().into();
+        point_error(bytes, on_curve, field)
+    })
+}
+
+pub fn try_g2(bytes: &[u8; 96], field: &'static str) -> Result<G2Affine, DeserializeError> {
+    Option::from(G2Affine::from_compressed(bytes)).ok_or_else(|| {
+        let on_curve = G2Affine::from_compressed_unchecked(bytes).is_some().into();
+        point_error(bytes, on_curve, field)
+    })
+}
+
+pub fn try_gt(bytes: &[u8; 288], field: &'static str) -> Result<Gt, DeserializeError> {
+    Option::from(Gt::from_compressed(bytes)).ok_or(DeserializeError::GtPoint(field))
+}
+
+#[cfg(feature = "serde")]
+pub fn serialize_bytes<S: serde::Serializer>(
+    bytes: &[u8],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    use base64::Engine;
+    use serde::Serialize;
+
+    if serializer.is_human_readable() {
+        let s = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+        serializer.serialize_str(&s)
+    } else {
+        serde_bytes::Bytes::new(bytes).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+pub fn deserialize_bytes<'de, D: serde::Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[u8; N], D::Error> {
+    use base64::Engine;
+    use core::convert::TryInto;
+    use serde::de::Error;
+    use serde::Deserialize;
+
+    let bytes = if deserializer.is_human_readable() {
+        let s = alloc::string::String::deserialize(deserializer)?;
+        base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(s)
+            .map_err(D::Error::custom)?
+    } else {
+        serde_bytes::ByteBuf::deserialize(deserializer)?.into_vec()
+    };
+
+    bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| D::Error::invalid_length(bytes.len(), &"a compressed byte representation"))
+}
+
+/// Implement `serde::Serialize` and `serde::Deserialize` using `to_bytes` and `from_bytes`.
+///
+/// Serializes to unpadded url-safe base64 for human readable formats, the same encoding as
+/// `Display` and `FromStr`, and to raw bytes otherwise.
+#[cfg(feature = "serde")]
+macro_rules! impl_serde {
+    ($type:ident, $len:expr) => {
+        impl serde::Serialize for $type {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                crate::util::serialize_bytes(&self.to_bytes(), serializer)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $type {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let bytes = crate::util::deserialize_bytes::<D, { $len }>(deserializer)?;
+                Option::from($type::from_bytes(&bytes))
+                    .ok_or_else(|| serde::de::Error::custom(concat!("invalid ", stringify!($type))))
+            }
+        }
+    };
+}
+
+/// The first `N` bytes of a slice, and whether the slice was long enough.
+///
+/// Shorter slices result in `N` zero bytes, such that they can still be parsed in constant time.
+pub fn prefix<const N: usize>(bytes: &[u8]) -> ([u8; N], subtle::Choice) {
+    let mut res = [0u8; N];
+    match bytes.get(..N) {
+        Some(prefix) => {
+            res.copy_from_slice(prefix);
+            (res, subtle::Choice::from(1))
+        }
+        None => (res, subtle::Choice::from(0)),
+    }
+}
+
+/// Decode unpadded url-safe base64 into exactly `N` bytes.
+pub fn decode_base64<const N: usize>(s: &str) -> Result<[u8; N], ParseError> {
+    use base64::Engine;
+
+    let found = s.len() * 6 / 8;
+    if s.len() != base64::encoded_len(N, false).unwrap_or(0) {
+        return Err(ParseError::Length { expected: N, found });
+    }
+
+    let mut res = [0u8; N];
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode_slice(s, &mut res)
+        .map_err(|_| ParseError::Base64)?;
+    Ok(res)
+}
+
+/// Implement `Display` and `FromStr` as unpadded url-safe base64 of `to_bytes`,
+/// parsing using `try_from_bytes`.
+macro_rules! impl_base64 {
+    ($type:ident, $len:expr) => {
+        impl core::fmt::Display for $type {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                let bytes = self.to_bytes();
+                let engine = &base64::engine::general_purpose::URL_SAFE_NO_PAD;
+                write!(f, "{}", base64::display::Base64Display::new(&bytes, engine))
+            }
+        }
+
+        impl core::str::FromStr for $type {
+            type Err = crate::error::ParseError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let bytes = crate::util::decode_base64::<{ $len }>(s)?;
+                Ok($type::try_from_bytes(&bytes)?)
+            }
+        }
+    };
+}
+
+/// Implement `from_prefix`, parsing from the start of a longer slice using `from_bytes`.
+///
+/// Types that are not `Copy`, such as secret keys, can not be combined in a `CtOption`.
+/// For these, list the compressed curve points that form the type and their sizes instead,
+/// which are decompressed in order.
+macro_rules! impl_from_prefix {
+    ($type:ident, $len:expr) => {
+        impl $type {
+            /// Deserialize from the start of a longer slice, returning the number of bytes consumed.
+            ///
+            /// Fails if the slice is shorter than the byte representation.
+            pub fn from_prefix(bytes: &[u8]) -> subtle::CtOption<($type, usize)> {
+                let (bytes, valid) = crate::util::prefix::<{ $len }>(bytes);
+                $type::from_bytes(&bytes).and_then(|x| subtle::CtOption::new((x, $len), valid))
+            }
+        }
+    };
+    ($type:ident, $len:expr, { $($field:ident: $point:ident[$size:expr]),+ $(,)? }) => {
+        impl $type {
+            /// Deserialize from the start of a longer slice, returning the number of bytes consumed.
+            ///
+            /// Fails if the slice is shorter than the byte representation.
+            pub fn from_prefix(bytes: &[u8]) -> subtle::CtOption<($type, usize)> {
+                let (bytes, mut valid) = crate::util::prefix::<{ $len }>(bytes);
+
+                let mut offset = 0;
+                $(
+                    let $field = $point::from_compressed(arrayref::array_ref![bytes, offset, $size]);
+                    valid &= $field.is_some();
+                    let $field = $field.unwrap_or($point::default());
+                    offset += $size;
+                )+
+                debug_assert_eq!(offset, $len);
+
+                subtle::CtOption::new(($type { $($field),+ }, $len), valid)
+            }
+        }
+    };
+}
+
+/// Implement `TryFrom<&[u8]>` checking the length of the slice, parsing using `try_from_bytes`.
+macro_rules! impl_try_from_slice {
+    ($type:ident, $len:expr) => {
+        impl core::convert::TryFrom<&[u8]> for $type {
+            type Error = crate::error::ParseError;
+
+            fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+                let bytes = <&[u8; $len]>::try_from(bytes).map_err(|_| {
+                    crate::error::ParseError::Length {
+                        expected: $len,
+                        found: bytes.len(),
+                    }
+                })?;
+                Ok($type::try_from_bytes(bytes)?)
+            }
+        }
+    };
+}
+
+/// Implement `TryFrom<Vec<u8>>` and `From<&T> for Vec<u8>`, for interoperability with owned buffers.
+#[cfg(feature = "alloc")]
+macro_rules! impl_vec_conversions {
+    ($type:ident) => {
+        impl core::convert::TryFrom<alloc::vec::Vec<u8>> for $type {
+            type Error = crate::error::ParseError;
+
+            fn try_from(bytes: alloc::vec::Vec<u8>) -> Result<Self, Self::Error> {
+                $type::try_from(bytes.as_slice())
+            }
+        }
+
+        impl From<&$type> for alloc::vec::Vec<u8> {
+            fn from(x: &$type) -> Self {
+                x.to_bytes().to_vec()
+            }
+        }
+    };
 }
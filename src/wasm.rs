@@ -0,0 +1,137 @@
+//! JavaScript bindings for the Waters scheme using [wasm-bindgen](https://crates.io/crates/wasm-bindgen).
+//!
+//! All keys, identities, messages and ciphertexts are passed as their compressed byte
+//! representations, as produced by the corresponding `to_bytes` functions.
+//! Randomness is taken from `crypto.getRandomValues` through `getrandom`.
+//! Requires the `wasm` feature.
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use rand::rngs::OsRng;
+use wasm_bindgen::prelude::*;
+
+use crate::error::ParseError;
+use crate::waters::{self, CipherText, Identity, Message, PublicKey, SecretKey, UserSecretKey};
+
+/// Public and secret key of the PKG, as generated by `setup`.
+#[wasm_bindgen]
+pub struct KeyPair {
+    pk: Vec<u8>,
+    sk: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl KeyPair {
+    /// The serialized public key.
+    #[wasm_bindgen(getter, js_name = publicKey)]
+    pub fn public_key(&self) -> Vec<u8> {
+        self.pk.clone()
+    }
+
+    /// The serialized secret key.
+    #[wasm_bindgen(getter, js_name = secretKey)]
+    pub fn secret_key(&self) -> Vec<u8> {
+        self.sk.clone()
+    }
+}
+
+/// Generate a keypair used by the Private Key Generator (PKG).
+#[wasm_bindgen]
+pub fn setup() -> KeyPair {
+    let (pk, sk) = waters::setup(&mut OsRng);
+
+    KeyPair {
+        pk: pk.to_bytes().to_vec(),
+        sk: sk.to_bytes().to_vec(),
+    }
+}
+
+/// Hash an identity to the 32-byte digest used by `extractUsk` and `encrypt`.
+#[wasm_bindgen(js_name = deriveIdentity)]
+pub fn derive_identity(id: &[u8]) -> Vec<u8> {
+    Identity::derive(id).to_hash_bytes().to_vec()
+}
+
+/// Extract a user secret key for a derived identity.
+#[wasm_bindgen(js_name = extractUsk)]
+pub fn extract_usk(pk: &[u8], sk: &[u8], id: &[u8]) -> Result<Vec<u8>, JsError> {
+    let pk = PublicKey::try_from(pk)?;
+    let sk = SecretKey::try_from(sk)?;
+    let usk = waters::extract_usk(&pk, &sk, &identity(id)?, &mut OsRng);
+
+    Ok(usk.to_bytes().to_vec())
+}
+
+/// Generate a random message to encrypt.
+#[wasm_bindgen(js_name = generateMessage)]
+pub fn generate_message() -> Vec<u8> {
+    Message::generate(&mut OsRng).to_bytes().to_vec()
+}
+
+/// Derive a 32-byte AES-256 key from a message.
+#[wasm_bindgen(js_name = deriveAesKey)]
+pub fn derive_aes_key(m: &[u8]) -> Result<Vec<u8>, JsError> {
+    Ok(Message::try_from(m)?.into_aes_key().to_vec())
+}
+
+/// Encrypt a message for a derived identity using the PKG public key.
+#[wasm_bindgen]
+pub fn encrypt(pk: &[u8], id: &[u8], m: &[u8]) -> Result<Vec<u8>, JsError> {
+    let pk = PublicKey::try_from(pk)?;
+    let m = Message::try_from(m)?;
+    let c = waters::encrypt(&pk, &identity(id)?, &m, &mut OsRng);
+
+    Ok(c.to_bytes().to_vec())
+}
+
+/// Decrypt a ciphertext to a message using a user secret key.
+#[wasm_bindgen]
+pub fn decrypt(usk: &[u8], c: &[u8]) -> Result<Vec<u8>, JsError> {
+    let usk = UserSecretKey::try_from(usk)?;
+    let c = CipherText::try_from(c)?;
+
+    Ok(waters::decrypt(&usk, &c).to_bytes().to_vec())
+}
+
+fn identity(bytes: &[u8]) -> Result<Identity, ParseError> {
+    let bytes =
+        <&[u8; waters::HASH_BYTE_LEN]>::try_from(bytes).map_err(|_| ParseError::Length {
+            expected: waters::HASH_BYTE_LEN,
+            found: bytes.len(),
+        })?;
+    Ok(Identity::from_hash_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    #[test]
+    fn eq_encrypt_decrypt() {
+        let keys = setup();
+        let id = derive_identity(ID.as_bytes());
+
+        let usk = extract_usk(&keys.public_key(), &keys.secret_key(), &id).unwrap();
+        let m = generate_message();
+        let c = encrypt(&keys.public_key(), &id, &m).unwrap();
+
+        assert_eq!(m, decrypt(&usk, &c).unwrap());
+        assert_eq!(derive_aes_key(&m).unwrap().len(), 32);
+    }
+
+    #[test]
+    fn identity_length() {
+        assert!(identity(&derive_identity(ID.as_bytes())).is_ok());
+        assert_eq!(
+            identity(ID.as_bytes()).unwrap_err(),
+            ParseError::Length {
+                expected: 32,
+                found: ID.len()
+            }
+        );
+    }
+}
@@ -4,30 +4,116 @@
 //!
 //! Uses [SHA3-256](https://crates.io/crates/tiny-keccak) for hashing to identities.
 //!
+//! Ciphertexts are not anonymous: given the public key, anyone can test whether a ciphertext
+//! was encrypted for a guessed identity. Use the `boyen_waters` scheme if this is a concern.
+//!
 //! The structure of the byte serialisation of the various datastructures is not guaranteed
 //! to remain constant between releases of this library.
 //! All operations in this library are implemented to run in constant time.
 
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
-use rand::Rng;
-use subtle::{Choice, ConditionallySelectable, CtOption};
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
 
+use crate::error::{DeserializeError, ParseError};
 use crate::util::*;
-use irmaseal_curve::{G1Affine, G1Projective, G2Affine, Gt};
+use core::convert::TryFrom;
+#[cfg(feature = "alloc")]
+use irmaseal_curve::G2Prepared;
+use irmaseal_curve::{G1Affine, G1Projective, G2Affine, G2Projective, Gt, Scalar};
 
 const HASH_BIT_LEN: usize = 256;
-const HASH_BYTE_LEN: usize = HASH_BIT_LEN / 8;
+/// Length in bytes of the digest an `Identity` is built from.
+pub const HASH_BYTE_LEN: usize = HASH_BIT_LEN / 8;
+
+/// Number of public key parameters `u[i]`, one for every bit of the identity digest.
+///
+/// Each parameter adds 48 bytes to the public key, so this determines most of its size.
+pub const CHUNKS: usize = HASH_BIT_LEN;
+
+// Entanglement and (de)serialization of the parameters assume one parameter per identity bit.
+const _: () = assert!(CHUNKS == HASH_BIT_LEN && HASH_BIT_LEN == HASH_BYTE_LEN * 8);
 
-const CHUNKS: usize = HASH_BIT_LEN;
+/// Number of identity bits handled per lookup in the windowed entanglement.
+#[cfg(feature = "alloc")]
+const WINDOW: usize = 4;
+
+#[cfg(feature = "alloc")]
+const _: () = assert!(CHUNKS % WINDOW == 0);
 
 const PARAMETERSIZE: usize = CHUNKS * 48;
-const PUBLICKEYSIZE: usize = 2 * 48 + 2 * 96 + PARAMETERSIZE;
+/// Length in bytes of a serialized `PublicKey`.
+pub const PUBLIC_KEY_BYTES: usize = 2 * 48 + 2 * 96 + PARAMETERSIZE;
+/// Length in bytes of a serialized `SecretKey`.
+pub const SECRET_KEY_BYTES: usize = 48;
+/// Length in bytes of a serialized `UserSecretKey`.
+pub const USER_SECRET_KEY_BYTES: usize = 48 + 96;
+/// Length in bytes of a serialized `Message`.
+pub const MESSAGE_BYTES: usize = 288;
+/// Length in bytes of a serialized `CipherText`.
+pub const CIPHERTEXT_BYTES: usize = 288 + 96 + 48;
+/// Length in bytes of a serialized `KemCipherText`.
+pub const KEM_CIPHERTEXT_BYTES: usize = 96 + 48;
+/// Length in bytes of a serialized `SealedBox`.
+pub const SEALED_BOX_BYTES: usize = KEM_CIPHERTEXT_BYTES + 32 + 32;
+
+/// Tag identifying the Waters scheme in versioned byte representations.
+pub const SCHEME_TAG: u8 = 1;
+/// Version of the format produced by `to_versioned_bytes`.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Domain separation tag for deriving shared secrets from messages.
+const KEM_DST: &[u8] = b"ibe-waters-kem";
+
+/// Domain separation tag for deriving the randomness of deterministic encryption.
+const DETERMINISTIC_DST: &[u8] = b"ibe-waters-deterministic";
+
+/// Domain separation tags for the mask and authentication key of sealed boxes,
+/// and for their authentication tag.
+const SEAL_MASK_DST: &[u8] = b"ibe-waters-seal-mask";
+const SEAL_MAC_DST: &[u8] = b"ibe-waters-seal-mac";
+const SEAL_TAG_DST: &[u8] = b"ibe-waters-seal-tag";
+
+/// Domain separation tag for mapping identities into the scalar field.
+const SCALAR_DST: &[u8] = b"ibe-waters-identity-scalar";
+
+/// Domain separation tag for deriving symmetric keys from messages.
+const DERIVE_KEY_DST: &[u8] = b"ibe-waters-derive-key";
+
+/// Domain separation tag for deriving AES-256 keys from messages.
+const AES_KEY_DST: &[u8] = b"ibe-waters-aes-key";
+
+/// Domain separation tag for public key fingerprints.
+const FINGERPRINT_DST: &[u8] = b"ibe-waters-fingerprint";
+
+/// Domain separation tag for identities derived from email addresses.
+const EMAIL_DOMAIN: &[u8] = b"ibe-waters-identity-email";
+
+/// Domain separation tag for identities derived from phone numbers.
+const PHONE_DOMAIN: &[u8] = b"ibe-waters-identity-phone";
+
+/// Domain separation tag for identities scoped to a validity epoch.
+const EPOCH_DOMAIN: &[u8] = b"ibe-waters-identity-epoch";
+
+/// Domain separation tag for identities derived from structured claims.
+#[cfg(feature = "alloc")]
+const CLAIMS_DOMAIN: &[u8] = b"ibe-waters-identity-claims";
+
+/// Domain separation tag for identities with a key version, used for revocation.
+const VERSION_DOMAIN: &[u8] = b"ibe-waters-identity-version";
+
+/// Domain separation tag for identities derived from UUIDs.
+const UUID_DOMAIN: &[u8] = b"ibe-waters-identity-uuid";
 
 /// Public key parameters used for entanglement with identities.
 struct Parameters([G1Affine; CHUNKS]);
 
 /// Public key parameters generated by the PKG used to encrypt messages.
-#[derive(Clone, Copy, PartialEq)]
+///
+/// The `Debug` output only shows the start of the `fingerprint` and the number of parameters.
+#[derive(Clone, Copy, Default)]
 pub struct PublicKey {
     g: G2Affine,
     g1: G1Affine,
@@ -36,71 +122,302 @@ pub struct PublicKey {
     u: Parameters,
 }
 
+/// Public key that keeps the parameters `u[i]` in their compressed form, to save memory.
+///
+/// Takes about 12 KiB, instead of about 26 KiB for `PublicKey`.
+/// The parameters are decompressed during every entanglement, which costs a square root
+/// per parameter: encryption using `encrypt_compact` is about 2.5 times slower than `encrypt`.
+/// Created using `PublicKey::compress_parameters` or `CompactPublicKey::from_bytes`,
+/// which validate the parameters, so they always decompress.
+#[derive(Clone, PartialEq)]
+pub struct CompactPublicKey {
+    g: G2Affine,
+    g1: G1Affine,
+    g2: G2Affine,
+    uprime: G1Affine,
+    u: [u8; PARAMETERSIZE],
+}
+
+/// Public key with the pairing `e(g1, g2)` and the entanglement tables precomputed,
+/// for repeated encryption.
+///
+/// Created using `PublicKey::prepare`. Only the public key itself is serialized,
+/// so the precomputed values can never be inconsistent with it.
+/// The `EntangleTables` take about 104 KiB of memory.
+#[cfg(feature = "alloc")]
+#[derive(Clone, PartialEq)]
+pub struct PreparedPublicKey {
+    pk: PublicKey,
+    e: Gt,
+    tables: EntangleTables,
+}
+
+/// Precomputed tables to entangle identities with the parameters of a public key,
+/// for servers that encrypt or extract heavily under one key.
+///
+/// For every window of 4 identity bits, the table holds the sums of all 16 subsets of the
+/// corresponding parameters `u[i]`, so an entanglement takes 64 constant-time table lookups and
+/// additions instead of 256 additions, which makes it about 2.5 times faster.
+/// The tables take about 104 KiB of memory, see `memory_size`.
+/// Created using `PublicKey::precompute_tables`, and included in `PreparedPublicKey`.
+#[cfg(feature = "alloc")]
+#[derive(Clone, PartialEq)]
+pub struct EntangleTables {
+    uprime: G1Affine,
+    tables: Vec<[G1Affine; 1 << WINDOW]>,
+}
+
 /// Secret key parameter generated by the PKG used to extract user secret keys.
-#[derive(Clone, Copy, PartialEq, Debug)]
+///
+/// Equality comparisons, both `==` and `ct_eq`, run in constant time.
+/// With the `zeroize` feature enabled, the key material is wiped on drop.
+/// The `Debug` output is redacted.
+#[derive(Clone)]
 pub struct SecretKey {
     g1prime: G1Affine,
 }
 
 /// Points on the paired curves that form the user secret key.
-#[derive(Clone, Copy, PartialEq, Debug)]
+///
+/// Equality comparisons, both `==` and `ct_eq`, run in constant time.
+/// With the `zeroize` feature enabled, the key material is wiped on drop.
+/// The `Debug` output is redacted.
+#[derive(Clone)]
 pub struct UserSecretKey {
     d1: G1Affine,
     d2: G2Affine,
 }
 
+/// Additive share of the secret key, for distributing the PKG over multiple parties.
+///
+/// Created using `SecretKey::split`. All shares are required to extract a user secret key.
+/// With the `zeroize` feature enabled, the key material is wiped on drop.
+/// The `Debug` output is redacted.
+#[derive(Clone)]
+pub struct SecretKeyShare {
+    g1prime: G1Affine,
+}
+
+/// Contribution of a single secret key share to a user secret key.
+///
+/// Created using `extract_partial_usk`, and combined using `UserSecretKey::combine`.
+/// The `Debug` output is redacted.
+#[derive(Clone, Copy, PartialEq)]
+pub struct PartialUserSecretKey {
+    d1: G1Affine,
+    d2: G2Affine,
+}
+
+/// Identity entangled with the public key parameters, for repeated encryption to the same identity.
+///
+/// Created using `PublicKey::prepare_identity`, and only valid for that public key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PreparedIdentity {
+    ucoll: G1Affine,
+}
+
+/// Prepared public key with a cache of prepared identities, for repeated encryption
+/// to a stable set of recipients.
+///
+/// Keeps at most `capacity` identities, evicting the least recently used one when full.
+/// Every cached identity takes about 100 bytes, in addition to the about 104 KiB of the
+/// prepared public key. Looking up an identity takes logarithmic time, but evicting one
+/// takes time linear in the capacity, so the capacity should cover the set of recipients.
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+pub struct EncryptCache {
+    ppk: PreparedPublicKey,
+    capacity: usize,
+    tick: u64,
+    entries: alloc::collections::BTreeMap<Identity, (PreparedIdentity, u64)>,
+}
+
+/// Request of a user to blindly extract a user secret key, to be sent to the PKG.
+///
+/// Contains the entanglement of the identity multiplied by a random scalar,
+/// which does not reveal the identity. Created using `blind_extract_request`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlindRequest {
+    x: G1Affine,
+}
+
+/// Blinding factor kept by the user to finalize a blind extraction.
+///
+/// Created using `blind_extract_request`, and used in `blind_extract_finalize`.
+/// The `Debug` output is redacted.
+#[derive(Clone)]
+pub struct BlindState {
+    b: Scalar,
+}
+
+/// Response of the PKG to a `BlindRequest`, to be sent back to the user.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlindResponse {
+    d1: G1Affine,
+    d2: G2Affine,
+}
+
+/// User secret key with the Miller loop precomputation for `d2`, for repeated decryption.
+///
+/// Created using `UserSecretKey::prepare`. Can not be serialized, nor wiped on drop.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct PreparedUserSecretKey {
+    d1: G1Affine,
+    d2: G2Prepared,
+}
+
+/// Ciphertext with the Miller loop precomputation for `c2`, for repeated decryption attempts.
+///
+/// Useful when one ciphertext is decrypted using many user secret keys.
+/// Created using `CipherText::prepare`. Can not be serialized.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct PreparedCipherText {
+    c1: Gt,
+    c2: G2Prepared,
+    c3: G1Affine,
+}
+
 /// Field parameters for an identity.
 ///
 /// Effectively a hash of an identity, mapped to the curve field.
 /// Together with the public key parameters generated by the PKG forms the user public key.
+///
+/// As an identity is public, equality, ordering and hashing do not run in constant time.
+/// Identities are ordered by lexicographic comparison of their digests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Identity([u8; HASH_BYTE_LEN]);
 
+/// Incremental hasher producing the same Identity as `Identity::derive` on the concatenated input.
+///
+/// Useful for identities derived from large inputs, which can then be hashed in parts.
+pub struct IdentityHasher(tiny_keccak::Keccak);
+
 /// A point on the paired curve that can be encrypted and decrypted.
 ///
-/// You can use the byte representation to derive an AES key.
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// Use `derive_key` to derive a symmetric key, such as an AES key, from it.
+/// The byte representation should not be used as a key directly, as it is not uniformly random.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub struct Message(Gt);
 
 /// Encrypted message. Can only be decrypted with an user secret key.
-#[derive(Clone, Copy, Debug, PartialEq)]
+///
+/// Equality, ordering and hashing are over the serialized bytes, for example to deduplicate
+/// ciphertexts in a `HashSet`. As ciphertexts are public, they do not run in constant time.
+/// Encrypting the same message twice results in different ciphertexts.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub struct CipherText {
     c1: Gt,
     c2: G2Affine,
     c3: G1Affine,
 }
 
+/// A 32-byte payload, such as a symmetric key or seed, encrypted for an identity using `seal`.
+///
+/// Serialized as the `KemCipherText`, the masked payload and a 32-byte authentication tag,
+/// taking 208 bytes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SealedBox {
+    c: KemCipherText,
+    payload: [u8; 32],
+    tag: [u8; 32],
+}
+
+/// Encapsulated shared secret, produced by `encapsulate_kem`.
+///
+/// Omits the masked message `c1` of `CipherText`, which takes 288 of its 432 bytes,
+/// so it only takes 144 bytes. Instead, the shared secret is derived from the mask `e(g1, g2)^t`
+/// itself. The price is that it can not carry a chosen `Message`, and can only be decrypted
+/// to the shared secret using `decapsulate_kem`.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct KemCipherText {
+    c2: G2Affine,
+    c3: G1Affine,
+}
+
+/// The Waters scheme, implementing the common `IbeScheme` interface.
+#[derive(Clone, Copy, Debug)]
+pub struct Waters;
+
+impl_ibe_scheme!(Waters);
+
+/// Encrypted message for multiple identities.
+///
+/// Shares the masked message `c1` and the randomness `c2` between all identities,
+/// and only contains a separate `c3` per identity.
+/// For N identities this takes 384 + 48 * N bytes, instead of 432 * N bytes for
+/// N independent ciphertexts. For example, 10 identities take 864 instead of 4320 bytes.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiCipherText {
+    c1: Gt,
+    c2: G2Affine,
+    c3: Vec<G1Affine>,
+}
+
 /// Generate a keypair used by the Private Key Generator (PKG).
-pub fn setup<R: Rng>(rng: &mut R) -> (PublicKey, SecretKey) {
+pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
     let g: G2Affine = rand_g2(rng).into();
 
-    let alpha = rand_scalar(rng);
-    let g2 = (g * alpha).into();
+    let alpha = rand_secret_scalar(rng);
+    let g2 = (g * *alpha).into();
+
+    // Generate all points on G1 in projective form, and convert them to affine using
+    // a single field inversion: `g1`, `uprime`, the parameters `u` and `g1prime`.
+    let mut ps = [G1Projective::identity(); CHUNKS + 3];
+    for p in ps[..CHUNKS + 2].iter_mut() {
+        *p = rand_g1(rng);
+    }
+    ps[CHUNKS + 2] = ps[0] * *alpha;
 
-    let g1 = rand_g1(rng).into();
-    let uprime = rand_g1(rng).into();
+    let mut affine = [G1Affine::identity(); CHUNKS + 3];
+    G1Projective::batch_normalize(&ps, &mut affine);
 
     let mut u = Parameters([G1Affine::default(); CHUNKS]);
-    for ui in u.0.iter_mut() {
-        *ui = rand_g1(rng).into();
-    }
+    u.0.copy_from_slice(&affine[2..CHUNKS + 2]);
 
     let pk = PublicKey {
         g,
-        g1,
+        g1: affine[0],
         g2,
-        uprime,
+        uprime: affine[1],
         u,
     };
 
-    let g1prime: G1Affine = (g1 * alpha).into();
-
-    let sk = SecretKey { g1prime };
+    let sk = SecretKey {
+        g1prime: affine[CHUNKS + 2],
+    };
 
     (pk, sk)
 }
 
+/// Generate a keypair used by the Private Key Generator (PKG), using the thread-local
+/// random number generator of `rand::thread_rng`, which is a CSPRNG seeded from the OS.
+///
+/// Convenience wrapper around `setup`. Requires the `std` feature.
+#[cfg(feature = "std")]
+pub fn setup_default() -> (PublicKey, SecretKey) {
+    setup(&mut rand::thread_rng())
+}
+
+/// Deterministically generate a keypair used by the Private Key Generator (PKG) from a seed.
+///
+/// Seeds a `ChaCha20Rng` from [rand_chacha](https://crates.io/crates/rand_chacha) 0.2
+/// using `SeedableRng::from_seed`, and passes it to `setup`.
+/// The seed should be generated by a cryptographically secure RNG and kept as secret as the secret key.
+pub fn setup_from_seed(seed: &[u8; 32]) -> (PublicKey, SecretKey) {
+    use rand::SeedableRng;
+
+    let mut rng = rand_chacha::ChaCha20Rng::from_seed(*seed);
+    setup(&mut rng)
+}
+
 /// Common operation used in extraction and encryption to entangle
 /// PublicKey with Identity into a point on G1.
+///
+/// Performs one conditional addition per identity bit. Without precomputed tables, a
+/// constant-time subset sum can not do with fewer; see `EntangleTables` for the windowed variant.
 fn entangle(pk: &PublicKey, v: &Identity) -> G1Projective {
     let mut ucoll: G1Projective = pk.uprime.into();
     for (ui, vi) in pk.u.0.iter().zip(bits(&v.0)) {
@@ -109,293 +426,3746 @@ fn entangle(pk: &PublicKey, v: &Identity) -> G1Projective {
     ucoll
 }
 
+/// Same as `entangle`, but decompresses each public key parameter just before using it.
+fn entangle_compact(cpk: &CompactPublicKey, v: &Identity) -> G1Projective {
+    let mut ucoll: G1Projective = cpk.uprime.into();
+    for (ui, vi) in cpk.u.chunks_exact(48).zip(bits(&v.0)) {
+        // Valid by construction, so the subgroup check can be skipped.
+        let ui = G1Affine::from_compressed_unchecked(array_ref![ui, 0, 48])
+            .unwrap_or(G1Affine::identity());
+        ucoll = G1Projective::conditional_select(&ucoll, &(ucoll + ui), vi);
+    }
+    ucoll
+}
+
+/// Sums of all subsets of each window of `WINDOW` consecutive public key parameters.
+#[cfg(feature = "alloc")]
+fn window_tables(pk: &PublicKey) -> EntangleTables {
+    let mut sums = Vec::with_capacity((CHUNKS / WINDOW) << WINDOW);
+    for us in pk.u.0.chunks_exact(WINDOW) {
+        let start = sums.len();
+        sums.push(G1Projective::identity());
+        for j in 1..1 << WINDOW {
+            // Extend the subset without its lowest bit with the parameter for that bit.
+            let rest = sums[start + (j & (j - 1))];
+            sums.push(rest + us[j.trailing_zeros() as usize]);
+        }
+    }
+
+    let mut res = vec![G1Affine::identity(); sums.len()];
+    G1Projective::batch_normalize(&sums, &mut res);
+
+    EntangleTables {
+        uprime: pk.uprime,
+        tables: res
+            .chunks_exact(1 << WINDOW)
+            .map(|table| *array_ref![table, 0, 1 << WINDOW])
+            .collect(),
+    }
+}
+
+/// Same as `entangle`, but performs a single addition per window of identity bits,
+/// using a constant-time lookup in the precomputed subset sums.
+#[cfg(feature = "alloc")]
+fn entangle_windowed(tables: &EntangleTables, v: &Identity) -> G1Projective {
+    let mut ucoll: G1Projective = tables.uprime.into();
+    let mut vbits = bits(&v.0);
+    for table in tables.tables.iter() {
+        let mut w = 0u8;
+        for (k, vi) in (&mut vbits).take(WINDOW).enumerate() {
+            w |= vi.unwrap_u8() << k;
+        }
+
+        let mut sum = G1Affine::identity();
+        for (j, tj) in table.iter().enumerate() {
+            sum.conditional_assign(tj, (j as u8).ct_eq(&w));
+        }
+        ucoll += sum;
+    }
+    ucoll
+}
+
 /// Extract an user secret key for a given identity.
-pub fn extract_usk<R: Rng>(
+pub fn extract_usk<R: RngCore + CryptoRng>(
     pk: &PublicKey,
     sk: &SecretKey,
     v: &Identity,
     rng: &mut R,
 ) -> UserSecretKey {
-    let r = rand_scalar(rng);
+    let r = rand_secret_scalar(rng);
     let ucoll = entangle(pk, v);
-    let d1 = (sk.g1prime + (ucoll * r)).into();
-    let d2 = (pk.g * r).into();
+    let d1 = (sk.g1prime + (ucoll * *r)).into();
+    let d2 = (pk.g * *r).into();
 
     UserSecretKey { d1, d2 }
 }
 
-/// Encrypt a message using the PKG public key and an identity.
-pub fn encrypt<R: Rng>(pk: &PublicKey, v: &Identity, m: &Message, rng: &mut R) -> CipherText {
-    let t = rand_scalar(rng);
+/// Extract an user secret key for a given identity, using the thread-local random number
+/// generator of `rand::thread_rng`, which is a CSPRNG seeded from the OS.
+///
+/// Convenience wrapper around `extract_usk`. Requires the `std` feature.
+#[cfg(feature = "std")]
+pub fn extract_usk_default(pk: &PublicKey, sk: &SecretKey, v: &Identity) -> UserSecretKey {
+    extract_usk(pk, sk, v, &mut rand::thread_rng())
+}
 
-    let c3coll = entangle(pk, v);
-    let c1 = irmaseal_curve::pairing(&pk.g1, &pk.g2) * t + m.0;
-    let c2 = (pk.g * t).into();
-    let c3 = (c3coll * t).into();
+/// Extract the contribution of a secret key share to the user secret key for a given identity.
+///
+/// Every party uses its own randomness, such that no single party knows the randomness of
+/// the combined user secret key.
+pub fn extract_partial_usk<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    share: &SecretKeyShare,
+    v: &Identity,
+    rng: &mut R,
+) -> PartialUserSecretKey {
+    let r = rand_secret_scalar(rng);
+    let ucoll = entangle(pk, v);
+    let d1 = (share.g1prime + (ucoll * *r)).into();
+    let d2 = (pk.g * *r).into();
 
-    CipherText { c1, c2, c3 }
+    PartialUserSecretKey { d1, d2 }
 }
 
-/// Decrypt ciphertext to a message using a user secret key.
-pub fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
-    let num = irmaseal_curve::pairing(&c.c3, &usk.d2);
-    let dem = irmaseal_curve::pairing(&usk.d1, &c.c2);
+/// Start extracting a user secret key for an identity, without revealing the identity to the PKG.
+///
+/// Blinds the entanglement of the identity with a random scalar `b`.
+/// Send the request to the PKG, and keep the state to finalize its response.
+///
+/// As the PKG does not learn the identity, it can not check whether the user is entitled to it.
+/// Blind extraction is thus only suitable when users are entitled to a key for any identity,
+/// or when entitlement is established by other means.
+pub fn blind_extract_request<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    rng: &mut R,
+) -> (BlindRequest, BlindState) {
+    let b = rand_secret_scalar(rng);
+    let x = (entangle(pk, v) * *b).into();
 
-    let m = c.c1 + num - dem;
-    Message(m)
+    (BlindRequest { x }, BlindState { b: *b })
 }
 
-impl PublicKey {
-    pub fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
-        let mut res = [0u8; PUBLICKEYSIZE];
-        let (g, g1, g2, uprime, u) = mut_array_refs![&mut res, 96, 48, 96, 48, PARAMETERSIZE];
-        *g = self.g.to_compressed();
-        *g1 = self.g1.to_compressed();
-        *g2 = self.g2.to_compressed();
-        *uprime = self.uprime.to_compressed();
-        *u = self.u.to_bytes();
-        res
-    }
-
-    pub fn from_bytes(bytes: &[u8; PUBLICKEYSIZE]) -> CtOption<Self> {
-        let (g, g1, g2, uprime, u) = array_refs![bytes, 96, 48, 96, 48, PARAMETERSIZE];
+/// Respond to a blind extraction request, as the PKG.
+///
+/// Computes `d1 = g1' + x * r` and `d2 = g * r` for the blinded entanglement `x`.
+pub fn blind_extract_respond<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    sk: &SecretKey,
+    req: &BlindRequest,
+    rng: &mut R,
+) -> BlindResponse {
+    let r = rand_secret_scalar(rng);
+    let d1 = (sk.g1prime + (req.x * *r)).into();
+    let d2 = (pk.g * *r).into();
 
-        let g = G2Affine::from_compressed(g);
-        let g1 = G1Affine::from_compressed(g1);
-        let g2 = G2Affine::from_compressed(g2);
-        let uprime = G1Affine::from_compressed(uprime);
-        let u = Parameters::from_bytes(u);
+    BlindResponse { d1, d2 }
+}
 
-        g.and_then(|g| {
-            g1.and_then(|g1| {
-                g2.and_then(|g2| {
-                    uprime.and_then(|uprime| {
-                        u.map(|u| PublicKey {
-                            g,
-                            g1,
-                            g2,
-                            uprime,
-                            u,
-                        })
-                    })
-                })
-            })
-        })
+/// Unblind the response of the PKG to the user secret key for the identity of the request.
+///
+/// The result can be checked using `UserSecretKey::verify`.
+pub fn blind_extract_finalize(state: &BlindState, resp: &BlindResponse) -> UserSecretKey {
+    UserSecretKey {
+        d1: resp.d1,
+        d2: (resp.d2 * state.b).into(),
     }
 }
 
-impl SecretKey {
-    pub fn to_bytes(&self) -> [u8; 48] {
-        self.g1prime.to_compressed()
-    }
+/// Extract user secret keys for many identities at once.
+///
+/// Gives the same result as calling `extract_usk` for each identity in order with the same `rng`,
+/// but precomputes the entanglement tables once and converts all points to affine
+/// with a single inversion per group. Intended for large batches.
+#[cfg(feature = "alloc")]
+pub fn extract_usk_batch<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    sk: &SecretKey,
+    ids: &[Identity],
+    rng: &mut R,
+) -> Vec<UserSecretKey> {
+    let tables = window_tables(pk);
 
-    pub fn from_bytes(bytes: &[u8; 48]) -> CtOption<Self> {
-        G1Affine::from_compressed(bytes).map(|g1prime| SecretKey { g1prime })
+    let mut d1s = Vec::with_capacity(ids.len());
+    let mut d2s = Vec::with_capacity(ids.len());
+    for v in ids.iter() {
+        let r = rand_secret_scalar(rng);
+        let ucoll = entangle_windowed(&tables, v);
+        d1s.push(sk.g1prime + (ucoll * *r));
+        d2s.push(pk.g * *r);
     }
+
+    normalize_g1(&d1s)
+        .into_iter()
+        .zip(normalize_g2(&d2s))
+        .map(|(d1, d2)| UserSecretKey { d1, d2 })
+        .collect()
 }
 
-impl UserSecretKey {
-    pub fn to_bytes(&self) -> [u8; 144] {
-        let mut res = [0u8; 144];
-        let (d1, d2) = mut_array_refs![&mut res, 48, 96];
-        *d1 = self.d1.to_compressed();
-        *d2 = self.d2.to_compressed();
-        res
+/// Extract user secret keys for a few related identities of one user at once, such as aliases.
+///
+/// Samples independent randomness `r` for every identity, as keys sharing `r` could be combined
+/// into a key for another identity. Gives the same result as calling `extract_usk` for each
+/// identity in order with the same `rng`, but converts all points to affine with a single
+/// inversion per group. Unlike `extract_usk_batch`, it does not precompute the entanglement
+/// tables, which only pays off for larger batches.
+#[cfg(feature = "alloc")]
+pub fn extract_usk_multi<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    sk: &SecretKey,
+    ids: &[Identity],
+    rng: &mut R,
+) -> Vec<UserSecretKey> {
+    let mut d1s = Vec::with_capacity(ids.len());
+    let mut d2s = Vec::with_capacity(ids.len());
+    for v in ids.iter() {
+        let r = rand_secret_scalar(rng);
+        d1s.push(sk.g1prime + (entangle(pk, v) * *r));
+        d2s.push(pk.g * *r);
     }
 
-    pub fn from_bytes(bytes: &[u8; 144]) -> CtOption<Self> {
-        let (d1, d2) = array_refs![bytes, 48, 96];
+    normalize_g1(&d1s)
+        .into_iter()
+        .zip(normalize_g2(&d2s))
+        .map(|(d1, d2)| UserSecretKey { d1, d2 })
+        .collect()
+}
 
-        let d1 = G1Affine::from_compressed(d1);
-        let d2 = G2Affine::from_compressed(d2);
+/// Encrypt a message using the PKG public key and an identity.
+pub fn encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    encrypt_with_scalar(pk, v, m, &rand_secret_scalar(rng))
+}
 
-        d1.and_then(|d1| d2.map(|d2| UserSecretKey { d1, d2 }))
-    }
+/// Encrypt a message using the PKG public key and an identity, using the thread-local
+/// random number generator of `rand::thread_rng`, which is a CSPRNG seeded from the OS.
+///
+/// Convenience wrapper around `encrypt`. Requires the `std` feature.
+#[cfg(feature = "std")]
+pub fn encrypt_default(pk: &PublicKey, v: &Identity, m: &Message) -> CipherText {
+    encrypt(pk, v, m, &mut rand::thread_rng())
 }
 
-impl Message {
-    /// Generate a random point on the paired curve.
-    pub fn generate<R: Rng>(rng: &mut R) -> Self {
-        Self(rand_gt(rng))
-    }
+/// Encrypt a message using the PKG public key, an identity and explicit randomness `t`.
+///
+/// **For producing test vectors only. Never use this in production.**
+/// Anyone who knows `t` can decrypt the ciphertext, and encrypting two messages with the
+/// same `t` reveals the difference between them. Requires the `test-vectors` feature.
+#[cfg(feature = "test-vectors")]
+pub fn encrypt_with_randomness(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    t: &Scalar,
+) -> CipherText {
+    encrypt_with_scalar(pk, v, m, t)
+}
 
-    pub fn to_bytes(&self) -> [u8; 288] {
-        self.0.to_compressed()
-    }
+/// Encrypt a message using the PKG public key and an identity, deriving the randomness
+/// from a seed instead of a random number generator.
+///
+/// The randomness `t` is derived by hashing the seed, the identity and the message into
+/// the scalar field, so the result is deterministic: identical inputs produce identical
+/// ciphertexts, which reveals to an observer that the same message was encrypted again.
+/// As long as any of the inputs differ, `t` differs, so a repeated seed does not leak the
+/// difference between messages like reusing `t` would.
+///
+/// The ciphertext is only as secure as the seed and the message are unpredictable: anyone
+/// who can guess both can recompute `t` and decrypt. Use a secret seed of at least 32 bytes,
+/// or encrypt a message generated using `Message::generate`. Intended for environments
+/// without a random number generator at encryption time; prefer `encrypt` otherwise.
+pub fn encrypt_deterministic(pk: &PublicKey, v: &Identity, m: &Message, seed: &[u8]) -> CipherText {
+    let t = hash_secret_scalar(
+        DETERMINISTIC_DST,
+        &[
+            &(seed.len() as u64).to_be_bytes(),
+            seed,
+            &v.0,
+            &m.to_bytes(),
+        ],
+    );
+    encrypt_with_scalar(pk, v, m, &t)
+}
 
-    pub fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
-        Gt::from_compressed(bytes).map(Message)
-    }
+fn encrypt_with_scalar(pk: &PublicKey, v: &Identity, m: &Message, t: &Scalar) -> CipherText {
+    let c3coll = entangle(pk, v);
+    let c1 = irmaseal_curve::pairing(&pk.g1, &pk.g2) * t + m.0;
+    let c2 = (pk.g * t).into();
+    let c3 = (c3coll * t).into();
+
+    CipherText { c1, c2, c3 }
 }
 
-impl Parameters {
-    pub fn to_bytes(&self) -> [u8; PARAMETERSIZE] {
-        let mut res = [0u8; PARAMETERSIZE];
-        for i in 0..CHUNKS {
-            *array_mut_ref![&mut res, i * 48, 48] = self.0[i].to_compressed();
-        }
-        res
-    }
+/// Generate a random message and encrypt it using the PKG public key and an identity.
+///
+/// Returns the ciphertext together with the message, such that the message can be used
+/// as a shared secret. A fresh message is generated for every call.
+pub fn encrypt_with_secret<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    rng: &mut R,
+) -> (CipherText, Message) {
+    let m = Message::generate(rng);
+    let c = encrypt(pk, v, &m, rng);
 
-    pub fn from_bytes(bytes: &[u8; PARAMETERSIZE]) -> CtOption<Self> {
-        let mut res = [G1Affine::default(); CHUNKS];
-        let mut is_some = Choice::from(1u8);
-        for i in 0..CHUNKS {
-            is_some &= G1Affine::from_compressed(array_ref![bytes, i * 48, 48])
-                .map(|s| {
-                    res[i] = s;
+    (c, m)
+}
+
+/// Encrypt a message using the prepared PKG public key and an identity.
+///
+/// Skips computing the pairing `e(g1, g2)`, which is the same for every encryption,
+/// and entangles the identity using the precomputed tables.
+#[cfg(feature = "alloc")]
+pub fn encrypt_prepared<R: RngCore + CryptoRng>(
+    ppk: &PreparedPublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let pk = &ppk.pk;
+    let t = rand_secret_scalar(rng);
+
+    let c3coll = entangle_windowed(&ppk.tables, v);
+    let c1 = ppk.e * *t + m.0;
+    let c2 = (pk.g * *t).into();
+    let c3 = (c3coll * *t).into();
+
+    CipherText { c1, c2, c3 }
+}
+
+/// Encrypt a message using the compact PKG public key and an identity.
+///
+/// Gives the same result as `encrypt` with the same `rng`, but decompresses the public key
+/// parameters on the fly. See `CompactPublicKey` for the costs.
+pub fn encrypt_compact<R: RngCore + CryptoRng>(
+    cpk: &CompactPublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let t = rand_secret_scalar(rng);
+
+    let c3coll = entangle_compact(cpk, v);
+    let c1 = irmaseal_curve::pairing(&cpk.g1, &cpk.g2) * *t + m.0;
+    let c2 = (cpk.g * *t).into();
+    let c3 = (c3coll * *t).into();
+
+    CipherText { c1, c2, c3 }
+}
+
+/// Encrypt a message for a prepared identity using the prepared PKG public key.
+///
+/// Skips both computing the pairing `e(g1, g2)` and entangling the identity,
+/// leaving a single exponentiation in Gt and two scalar multiplications.
+/// The identity must have been prepared using the same public key.
+#[cfg(feature = "alloc")]
+pub fn encrypt_prepared_identity<R: RngCore + CryptoRng>(
+    ppk: &PreparedPublicKey,
+    pid: &PreparedIdentity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let t = rand_secret_scalar(rng);
+
+    let c1 = ppk.e * *t + m.0;
+    let c2 = (ppk.pk.g * *t).into();
+    let c3 = (pid.ucoll * *t).into();
+
+    CipherText { c1, c2, c3 }
+}
+
+/// Encrypt many messages, each for its own identity, using the PKG public key.
+///
+/// Gives the same result as calling `encrypt` for each pair in order with the same `rng`,
+/// but computes the pairing and entanglement tables once and converts all points to affine
+/// with a single inversion per group. Intended for large batches.
+#[cfg(feature = "alloc")]
+pub fn encrypt_batch<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    items: &[(Identity, Message)],
+    rng: &mut R,
+) -> Vec<CipherText> {
+    let ppk = pk.prepare();
+
+    let mut c1s = Vec::with_capacity(items.len());
+    let mut c2s = Vec::with_capacity(items.len());
+    let mut c3s = Vec::with_capacity(items.len());
+    for (v, m) in items.iter() {
+        let t = rand_secret_scalar(rng);
+        c1s.push(ppk.e * *t + m.0);
+        c2s.push(pk.g * *t);
+        c3s.push(entangle_windowed(&ppk.tables, v) * *t);
+    }
+
+    c1s.into_iter()
+        .zip(normalize_g2(&c2s))
+        .zip(normalize_g1(&c3s))
+        .map(|((c1, c2), c3)| CipherText { c1, c2, c3 })
+        .collect()
+}
+
+/// Encrypt the same message for many identities in parallel using the PKG public key.
+///
+/// Unlike `encrypt_multi`, produces an independent ciphertext for each identity.
+/// Each ciphertext is encrypted using its own `ChaCha20Rng`, seeded from `rng`.
+/// Requires the `parallel` feature.
+#[cfg(feature = "parallel")]
+pub fn encrypt_batch_par<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    ids: &[Identity],
+    m: &Message,
+    rng: &mut R,
+) -> Vec<CipherText> {
+    use rand::{Rng, SeedableRng};
+    use rayon::prelude::*;
+
+    let ppk = pk.prepare();
+    let seeds: Vec<[u8; 32]> = ids.iter().map(|_| rng.gen()).collect();
+
+    ids.par_iter()
+        .zip(seeds.par_iter())
+        .map(|(v, seed)| {
+            let mut rng = rand_chacha::ChaCha20Rng::from_seed(*seed);
+            encrypt_prepared(&ppk, v, m, &mut rng)
+        })
+        .collect()
+}
+
+/// Convert many points to affine using a single field inversion.
+#[cfg(feature = "alloc")]
+fn normalize_g1(ps: &[G1Projective]) -> Vec<G1Affine> {
+    let mut res = vec![G1Affine::identity(); ps.len()];
+    G1Projective::batch_normalize(ps, &mut res);
+    res
+}
+
+/// Convert many points to affine using a single field inversion.
+#[cfg(feature = "alloc")]
+fn normalize_g2(ps: &[G2Projective]) -> Vec<G2Affine> {
+    let mut res = vec![G2Affine::identity(); ps.len()];
+    G2Projective::batch_normalize(ps, &mut res);
+    res
+}
+
+/// Decrypt ciphertext to a message using a user secret key.
+pub fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
+    let num = irmaseal_curve::pairing(&c.c3, &usk.d2);
+    let dem = irmaseal_curve::pairing(&usk.d1, &c.c2);
+
+    let m = c.c1 + num - dem;
+    Message(m)
+}
+
+/// Decrypt ciphertext to a message using a prepared user secret key.
+///
+/// Computes both pairings in a single multi Miller loop with one final exponentiation.
+#[cfg(feature = "alloc")]
+pub fn decrypt_prepared(pusk: &PreparedUserSecretKey, c: &CipherText) -> Message {
+    let num_dem = irmaseal_curve::multi_miller_loop(&[
+        (&c.c3, &pusk.d2),
+        (&-pusk.d1, &G2Prepared::from(c.c2)),
+    ])
+    .final_exponentiation();
+
+    Message(c.c1 + num_dem)
+}
+
+/// Decrypt a prepared ciphertext to a message using a user secret key.
+///
+/// Computes both pairings in a single multi Miller loop with one final exponentiation.
+#[cfg(feature = "alloc")]
+pub fn decrypt_prepared_ciphertext(usk: &UserSecretKey, pc: &PreparedCipherText) -> Message {
+    let num_dem = irmaseal_curve::multi_miller_loop(&[
+        (&pc.c3, &G2Prepared::from(usk.d2)),
+        (&-usk.d1, &pc.c2),
+    ])
+    .final_exponentiation();
+
+    Message(pc.c1 + num_dem)
+}
+
+/// Check that a ciphertext was encrypted for an identity, i.e. e(c3, g) = e(entangle(v), c2).
+fn is_consistent(pk: &PublicKey, v: &Identity, c: &CipherText) -> Choice {
+    let ucoll: G1Affine = entangle(pk, v).into();
+
+    pairing_product_is_identity(&[(c.c3, pk.g), (-ucoll, c.c2)])
+}
+
+/// Check that a user secret key is valid for an identity, i.e. e(d1, g) = e(g1, g2) * e(entangle(v), d2).
+pub(crate) fn is_valid_usk(pk: &PublicKey, v: &Identity, usk: &UserSecretKey) -> Choice {
+    let ucoll: G1Affine = entangle(pk, v).into();
+
+    pairing_product_is_identity(&[(usk.d1, pk.g), (-pk.g1, pk.g2), (-ucoll, usk.d2)])
+}
+
+/// Check that all user secret keys are valid for their identities, using a random linear
+/// combination of the validity equations such that only `n + 2` pairings are needed.
+///
+/// Not constant time in the number of keys. An invalid key passes with negligible probability.
+#[cfg(feature = "alloc")]
+pub(crate) fn are_valid_usks<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    items: &[(Identity, &UserSecretKey)],
+    rng: &mut R,
+) -> Choice {
+    let mut d1 = G1Projective::identity();
+    let mut rsum = Scalar::zero();
+    let mut terms = Vec::with_capacity(items.len() + 1);
+    for (v, usk) in items.iter() {
+        let r = rand_scalar(rng);
+        d1 += usk.d1 * r;
+        rsum += r;
+        terms.push(-(entangle(pk, v) * r));
+    }
+    terms.push(d1);
+
+    let terms = normalize_g1(&terms);
+    let mut pairs: Vec<(G1Affine, G2Affine)> = items
+        .iter()
+        .zip(terms.iter())
+        .map(|((_, usk), t)| (*t, usk.d2))
+        .collect();
+    pairs.push((terms[items.len()], pk.g));
+    pairs.push((-pk.g1, (pk.g2 * rsum).into()));
+
+    pairing_product_is_identity(&pairs)
+}
+
+/// Decrypt ciphertext to a message using a user secret key,
+/// verifying that the ciphertext is well-formed for the identity.
+///
+/// Returns `None` if the ciphertext was not encrypted for this identity,
+/// or if its components have been tampered with.
+/// Note that tampering with `c1` can not be detected, as it only masks the message.
+pub fn decrypt_checked(
+    pk: &PublicKey,
+    usk: &UserSecretKey,
+    v: &Identity,
+    c: &CipherText,
+) -> CtOption<Message> {
+    CtOption::new(decrypt(usk, c), is_consistent(pk, v, c))
+}
+
+/// Decrypt a ciphertext only if it was encrypted for the identity of the user secret key,
+/// for example when reading from an inbox shared with other identities.
+///
+/// Same as `decrypt_checked`, but returns an `Option`, so whether the ciphertext was
+/// addressed to the identity is not hidden by timing.
+pub fn decrypt_for(
+    pk: &PublicKey,
+    usk: &UserSecretKey,
+    v: &Identity,
+    c: &CipherText,
+) -> Option<Message> {
+    decrypt_checked(pk, usk, v, c).into()
+}
+
+/// Try to decrypt a ciphertext using each of the user secret keys of a receiver with multiple identities.
+///
+/// The `i`-th user secret key must belong to the `i`-th identity.
+/// Returns the index of the first key for which the ciphertext was encrypted, with the decrypted message.
+///
+/// As `decrypt` always returns some message, this uses `decrypt_checked` to detect which
+/// identity the ciphertext was encrypted for, which is why the public key and identities are required.
+/// Which key succeeded is not hidden by timing.
+///
+/// # Panics
+/// If the numbers of identities and user secret keys differ.
+pub fn try_decrypt_any(
+    pk: &PublicKey,
+    ids: &[Identity],
+    usks: &[UserSecretKey],
+    c: &CipherText,
+) -> Option<(usize, Message)> {
+    assert_eq!(
+        ids.len(),
+        usks.len(),
+        "every identity must have exactly one user secret key"
+    );
+
+    ids.iter()
+        .zip(usks.iter())
+        .enumerate()
+        .find_map(|(i, (v, usk))| Option::from(decrypt_checked(pk, usk, v, c)).map(|m| (i, m)))
+}
+
+/// Decrypt a ciphertext produced by `encapsulate` to the same 32-byte shared secret,
+/// verifying that the ciphertext is well-formed for the identity.
+///
+/// See `decrypt_checked` for which modifications can be detected.
+pub fn decrypt_to_shared_key(
+    pk: &PublicKey,
+    usk: &UserSecretKey,
+    v: &Identity,
+    c: &CipherText,
+) -> CtOption<[u8; 32]> {
+    CtOption::new(kdf(&decrypt(usk, c)), is_consistent(pk, v, c))
+}
+
+/// Encrypt a message for multiple identities using the PKG public key.
+///
+/// The same randomness is used for all identities, which is what allows sharing
+/// the masked message between them. The recipients of the ciphertext can see
+/// the number of recipients, and at which index their identity is.
+#[cfg(feature = "alloc")]
+pub fn encrypt_multi<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    ids: &[Identity],
+    m: &Message,
+    rng: &mut R,
+) -> MultiCipherText {
+    let t = rand_secret_scalar(rng);
+
+    let c1 = irmaseal_curve::pairing(&pk.g1, &pk.g2) * *t + m.0;
+    let c2 = (pk.g * *t).into();
+    let c3s: Vec<G1Projective> = ids.iter().map(|v| entangle(pk, v) * *t).collect();
+
+    MultiCipherText {
+        c1,
+        c2,
+        c3: normalize_g1(&c3s),
+    }
+}
+
+/// Decrypt the ciphertext for the identity at `index` using the user secret key for that identity.
+///
+/// Returns `None` if the index is out of bounds.
+#[cfg(feature = "alloc")]
+pub fn decrypt_multi(usk: &UserSecretKey, mc: &MultiCipherText, index: usize) -> Option<Message> {
+    mc.get(index).map(|c| decrypt(usk, &c))
+}
+
+/// Generate a random message, encrypt it and derive a 32-byte shared secret from it.
+///
+/// The shared secret can directly be used as a symmetric key, for example for AES-256.
+pub fn encapsulate<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    rng: &mut R,
+) -> (CipherText, [u8; 32]) {
+    let (c, m) = encrypt_with_secret(pk, v, rng);
+
+    (c, kdf(&m))
+}
+
+/// Decrypt a ciphertext produced by `encapsulate` to the same 32-byte shared secret.
+pub fn decapsulate(usk: &UserSecretKey, c: &CipherText) -> [u8; 32] {
+    kdf(&decrypt(usk, c))
+}
+
+/// Derive a 32-byte shared secret and encapsulate it in a compact ciphertext.
+///
+/// Like `encapsulate`, but the ciphertext takes 144 instead of 432 bytes,
+/// as the shared secret is derived from the randomness of the encryption rather than
+/// from an encrypted random message. Not interchangeable with `encapsulate`.
+pub fn encapsulate_kem<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    rng: &mut R,
+) -> (KemCipherText, [u8; 32]) {
+    let t = rand_secret_scalar(rng);
+
+    let k = Message(irmaseal_curve::pairing(&pk.g1, &pk.g2) * *t);
+    let c2 = (pk.g * *t).into();
+    let c3 = (entangle(pk, v) * *t).into();
+
+    (KemCipherText { c2, c3 }, kdf(&k))
+}
+
+/// Decrypt a ciphertext produced by `encapsulate_kem` to the same 32-byte shared secret.
+pub fn decapsulate_kem(usk: &UserSecretKey, c: &KemCipherText) -> [u8; 32] {
+    let num = irmaseal_curve::pairing(&usk.d1, &c.c2);
+    let dem = irmaseal_curve::pairing(&c.c3, &usk.d2);
+
+    kdf(&Message(num - dem))
+}
+
+/// Encrypt a 32-byte payload, such as a symmetric key or seed, for an identity.
+///
+/// Encapsulates a shared secret using `encapsulate_kem`, from which a mask and an authentication
+/// key are derived. The payload is masked by XOR, which is secure as the shared secret is fresh
+/// for every box, and authenticated together with the encapsulation using a domain-separated
+/// sha3-256 keyed with the authentication key. Smaller than the blobs of the `hybrid` module,
+/// and does not need the `aead` feature.
+pub fn seal<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    payload: &[u8; 32],
+    rng: &mut R,
+) -> SealedBox {
+    let (c, k) = encapsulate_kem(pk, v, rng);
+
+    let mask = sha3_256_dst(SEAL_MASK_DST, &[&k]);
+    let mut masked = [0u8; 32];
+    for ((x, p), m) in masked.iter_mut().zip(payload.iter()).zip(mask.iter()) {
+        *x = p ^ m;
+    }
+    let tag = seal_tag(&k, &c, &masked);
+
+    SealedBox {
+        c,
+        payload: masked,
+        tag,
+    }
+}
+
+/// Decrypt a `SealedBox` to its payload using a user secret key.
+///
+/// Returns `None` if the box was not sealed for the identity of the user secret key,
+/// or if it has been tampered with.
+pub fn open(usk: &UserSecretKey, sealed: &SealedBox) -> Option<[u8; 32]> {
+    let k = decapsulate_kem(usk, &sealed.c);
+
+    let mask = sha3_256_dst(SEAL_MASK_DST, &[&k]);
+    let mut payload = [0u8; 32];
+    for ((x, p), m) in payload
+        .iter_mut()
+        .zip(sealed.payload.iter())
+        .zip(mask.iter())
+    {
+        *x = p ^ m;
+    }
+    let valid = seal_tag(&k, &sealed.c, &sealed.payload).ct_eq(&sealed.tag);
+
+    CtOption::new(payload, valid).into()
+}
+
+/// Authentication tag of a sealed box, keyed with a key derived from the shared secret.
+fn seal_tag(k: &[u8; 32], c: &KemCipherText, masked: &[u8; 32]) -> [u8; 32] {
+    let mac_key = sha3_256_dst(SEAL_MAC_DST, &[k]);
+    sha3_256_dst(SEAL_TAG_DST, &[&mac_key, &c.to_bytes(), masked])
+}
+
+/// Hash a message to a 32-byte shared secret using a domain-separated sha3-256.
+fn kdf(m: &Message) -> [u8; 32] {
+    sha3_256_dst(KEM_DST, &[&m.to_bytes()])
+}
+
+#[cfg(feature = "serde")]
+impl_serde!(PublicKey, PUBLIC_KEY_BYTES);
+#[cfg(feature = "serde")]
+impl_serde!(SecretKey, SECRET_KEY_BYTES);
+#[cfg(feature = "serde")]
+impl_serde!(UserSecretKey, USER_SECRET_KEY_BYTES);
+#[cfg(feature = "serde")]
+impl_serde!(CipherText, CIPHERTEXT_BYTES);
+
+impl_base64!(PublicKey, PUBLIC_KEY_BYTES);
+impl_base64!(UserSecretKey, USER_SECRET_KEY_BYTES);
+impl_base64!(CipherText, CIPHERTEXT_BYTES);
+
+impl_try_from_slice!(PublicKey, PUBLIC_KEY_BYTES);
+impl_try_from_slice!(SecretKey, SECRET_KEY_BYTES);
+impl_try_from_slice!(UserSecretKey, USER_SECRET_KEY_BYTES);
+impl_try_from_slice!(Message, MESSAGE_BYTES);
+impl_try_from_slice!(CipherText, CIPHERTEXT_BYTES);
+
+#[cfg(feature = "alloc")]
+impl_vec_conversions!(PublicKey);
+#[cfg(feature = "alloc")]
+impl_vec_conversions!(SecretKey);
+#[cfg(feature = "alloc")]
+impl_vec_conversions!(UserSecretKey);
+#[cfg(feature = "alloc")]
+impl_vec_conversions!(Message);
+#[cfg(feature = "alloc")]
+impl_vec_conversions!(CipherText);
+
+impl_from_prefix!(PublicKey, PUBLIC_KEY_BYTES);
+impl_from_prefix!(Message, MESSAGE_BYTES);
+impl_from_prefix!(CipherText, CIPHERTEXT_BYTES);
+impl_from_prefix!(SecretKey, SECRET_KEY_BYTES, { g1prime: G1Affine[48] });
+impl_from_prefix!(UserSecretKey, USER_SECRET_KEY_BYTES, { d1: G1Affine[48], d2: G2Affine[96] });
+
+/// Prefix a byte representation with the scheme tag and format version.
+fn to_versioned<const N: usize>(body: &[u8]) -> [u8; N] {
+    let mut res = [0u8; N];
+    res[0] = SCHEME_TAG;
+    res[1] = FORMAT_VERSION;
+    res[2..].copy_from_slice(body);
+    res
+}
+
+/// Check the scheme tag and format version of a versioned byte representation, returning its body.
+fn from_versioned(bytes: &[u8], len: usize) -> Result<&[u8], ParseError> {
+    match bytes {
+        [SCHEME_TAG, FORMAT_VERSION, body @ ..] => Ok(body),
+        [SCHEME_TAG, version, ..] => Err(ParseError::UnsupportedVersion(*version)),
+        [tag, _, ..] => Err(ParseError::Scheme(*tag)),
+        _ => Err(ParseError::Length {
+            expected: 2 + len,
+            found: bytes.len(),
+        }),
+    }
+}
+
+impl PublicKey {
+    pub fn to_bytes(&self) -> [u8; PUBLIC_KEY_BYTES] {
+        let mut res = [0u8; PUBLIC_KEY_BYTES];
+        let (g, g1, g2, uprime, u) = mut_array_refs![&mut res, 96, 48, 96, 48, PARAMETERSIZE];
+        *g = self.g.to_compressed();
+        *g1 = self.g1.to_compressed();
+        *g2 = self.g2.to_compressed();
+        *uprime = self.uprime.to_compressed();
+        *u = self.u.to_bytes();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; PUBLIC_KEY_BYTES]) -> CtOption<Self> {
+        let (g, g1, g2, uprime, u) = array_refs![bytes, 96, 48, 96, 48, PARAMETERSIZE];
+
+        let g = G2Affine::from_compressed(g);
+        let g1 = G1Affine::from_compressed(g1);
+        let g2 = G2Affine::from_compressed(g2);
+        let uprime = G1Affine::from_compressed(uprime);
+        let u = Parameters::from_bytes(u);
+
+        g.and_then(|g| {
+            g1.and_then(|g1| {
+                g2.and_then(|g2| {
+                    uprime.and_then(|uprime| {
+                        u.map(|u| PublicKey {
+                            g,
+                            g1,
+                            g2,
+                            uprime,
+                            u,
+                        })
+                    })
                 })
-                .is_some();
+            })
+        })
+        .and_then(|pk| CtOption::new(pk, pk.has_no_identity_points()))
+    }
+
+    /// Check that `g`, `g1`, `g2` and `uprime` are not the identity, as a malicious PKG could
+    /// use the identity to make encryption and extraction degenerate.
+    fn has_no_identity_points(&self) -> Choice {
+        !self.g.is_identity()
+            & !self.g1.is_identity()
+            & !self.g2.is_identity()
+            & !self.uprime.is_identity()
+    }
+
+    /// Validate the public key parameters.
+    ///
+    /// Checks that:
+    ///  * `g`, `g1`, `g2`, `uprime` and every `u[i]` are on the curve and in the prime-order subgroup;
+    ///  * `g`, `g1`, `g2` and `uprime` are not the identity.
+    ///
+    /// `from_bytes` already performs these checks, so this is only needed for public keys
+    /// that were not deserialized.
+    pub fn validate(&self) -> Choice {
+        let mut valid = self.has_no_identity_points();
+
+        for p in [self.g, self.g2].iter() {
+            valid &= p.is_on_curve() & p.is_torsion_free();
         }
-        CtOption::new(Parameters(res), is_some)
+        for p in [self.g1, self.uprime].iter().chain(self.u.0.iter()) {
+            valid &= p.is_on_curve() & p.is_torsion_free();
+        }
+        valid
+    }
+
+    /// The generator `g` of G2.
+    pub fn g(&self) -> G2Affine {
+        self.g
+    }
+
+    /// The generator `g1` of G1.
+    pub fn g1(&self) -> G1Affine {
+        self.g1
+    }
+
+    /// The point `g2 = g * alpha` on G2, where `alpha` is the PKG master secret.
+    pub fn g2(&self) -> G2Affine {
+        self.g2
+    }
+
+    /// The parameter `u'` the entanglement of every identity starts from.
+    pub fn uprime(&self) -> G1Affine {
+        self.uprime
+    }
+
+    /// The parameters `u[i]` entangled with the bits of identities, in order.
+    ///
+    /// `u[0]` belongs to the most significant bit of the last byte of the identity digest,
+    /// `u[7]` to its least significant bit, `u[8]` to the most significant bit of the byte before, etc.
+    pub fn parameters(&self) -> impl Iterator<Item = &G1Affine> {
+        self.u.0.iter()
+    }
+
+    /// The parameter `u[i]`, or `None` if `i` is at least `CHUNKS`.
+    pub fn parameter(&self, i: usize) -> Option<&G1Affine> {
+        self.u.0.get(i)
+    }
+
+    /// Entangle an identity with the public key parameters,
+    /// i.e. `u' + sum(u[i])` over the bits `i` set in the identity.
+    pub fn entangle_identity(&self, v: &Identity) -> G1Affine {
+        entangle(self, v).into()
+    }
+
+    /// Length in bytes of the serialized public key, `PUBLIC_KEY_BYTES`.
+    pub const fn serialized_len() -> usize {
+        PUBLIC_KEY_BYTES
+    }
+
+    /// Size in bytes of a public key in memory, for capacity planning when keeping many keys.
+    ///
+    /// A public key does not allocate, so this is its stack size, which is dominated by
+    /// the uncompressed parameters. See `CompactPublicKey` to save memory.
+    pub const fn in_memory_size() -> usize {
+        core::mem::size_of::<PublicKey>()
+    }
+
+    /// Keep the public key parameters compressed, to save memory at the cost of slower encryption.
+    pub fn compress_parameters(&self) -> CompactPublicKey {
+        CompactPublicKey {
+            g: self.g,
+            g1: self.g1,
+            g2: self.g2,
+            uprime: self.uprime,
+            u: self.u.to_bytes(),
+        }
+    }
+
+    /// Entangle an identity with the public key parameters once, for repeated encryption to it.
+    pub fn prepare_identity(&self, v: &Identity) -> PreparedIdentity {
+        PreparedIdentity {
+            ucoll: self.entangle_identity(v),
+        }
+    }
+
+    /// Precompute the tables to entangle identities with the parameters of this public key.
+    ///
+    /// Costs about as much as 64 entanglements, see `EntangleTables`.
+    /// `prepare` also precomputes these tables.
+    #[cfg(feature = "alloc")]
+    pub fn precompute_tables(&self) -> EntangleTables {
+        window_tables(self)
+    }
+
+    /// Precompute the pairing `e(g1, g2)` and the entanglement tables used in every encryption.
+    #[cfg(feature = "alloc")]
+    pub fn prepare(&self) -> PreparedPublicKey {
+        PreparedPublicKey {
+            pk: *self,
+            e: irmaseal_curve::pairing(&self.g1, &self.g2),
+            tables: self.precompute_tables(),
+        }
+    }
+
+    /// A short fingerprint of the public key, for pinning and comparing it out of band.
+    ///
+    /// Hashes the serialized public key with a domain-separated sha3-256.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        sha3_256_dst(FINGERPRINT_DST, &[&self.to_bytes()])
+    }
+
+    /// Check whether the `fingerprint` of this public key equals a pinned fingerprint.
+    ///
+    /// Takes the fingerprint rather than a second public key, as pinning usually only stores
+    /// the fingerprint. To compare two keys, pass `other.fingerprint()`, or use `==`.
+    /// Public keys are not secret, so this comparison is not constant time.
+    pub fn eq_fingerprint(&self, fingerprint: &[u8; 32]) -> bool {
+        self.fingerprint() == *fingerprint
+    }
+
+    /// Check whether this public key has the same generators `g`, `g1` and `g2` as another,
+    /// i.e. whether both stem from the same master secret, ignoring the parameters `u'` and `u[i]`.
+    ///
+    /// Helps diagnose clients that combine the generators of one key with the parameters
+    /// of another. Use `==` to compare complete public keys.
+    pub fn same_generators(&self, other: &Self) -> bool {
+        self.g == other.g && self.g1 == other.g1 && self.g2 == other.g2
+    }
+
+    /// The `fingerprint` as a lowercase hex string, for display.
+    #[cfg(feature = "alloc")]
+    pub fn fingerprint_hex(&self) -> alloc::string::String {
+        use core::fmt::Write;
+
+        let mut res = alloc::string::String::with_capacity(64);
+        for b in self.fingerprint().iter() {
+            let _ = write!(res, "{:02x}", b);
+        }
+        res
+    }
+
+    /// Serialize prefixed with `SCHEME_TAG` and `FORMAT_VERSION`, for persistent storage.
+    ///
+    /// Unlike `to_bytes`, future changes to the format can be detected by `from_versioned_bytes`.
+    pub fn to_versioned_bytes(&self) -> [u8; 2 + PUBLIC_KEY_BYTES] {
+        to_versioned(&self.to_bytes())
+    }
+
+    /// Deserialize the output of `to_versioned_bytes`, rejecting other schemes and format versions.
+    ///
+    /// Unlike `from_bytes`, this function does not run in constant time.
+    pub fn from_versioned_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        Self::try_from(from_versioned(bytes, PUBLIC_KEY_BYTES)?)
+    }
+
+    /// Deserialize a public key, reporting which field failed to decompress.
+    ///
+    /// Unlike `from_bytes`, this function does not run in constant time.
+    pub fn try_from_bytes(bytes: &[u8; PUBLIC_KEY_BYTES]) -> Result<Self, DeserializeError> {
+        let (g, g1, g2, uprime, u) = array_refs![bytes, 96, 48, 96, 48, PARAMETERSIZE];
+
+        let pk = PublicKey {
+            g: try_g2(g, "g")?,
+            g1: try_g1(g1, "g1")?,
+            g2: try_g2(g2, "g2")?,
+            uprime: try_g1(uprime, "uprime")?,
+            u: Parameters::try_from_bytes(u)?,
+        };
+
+        let points = [
+            (pk.g.is_identity(), "g"),
+            (pk.g1.is_identity(), "g1"),
+            (pk.g2.is_identity(), "g2"),
+            (pk.uprime.is_identity(), "uprime"),
+        ];
+        for (is_identity, field) in points.iter() {
+            if bool::from(*is_identity) {
+                return Err(DeserializeError::IdentityPoint(field));
+            }
+        }
+        Ok(pk)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PreparedPublicKey {
+    /// The public key this was prepared from.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.pk
+    }
+
+    pub fn to_bytes(&self) -> [u8; PUBLIC_KEY_BYTES] {
+        self.pk.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8; PUBLIC_KEY_BYTES]) -> CtOption<Self> {
+        PublicKey::from_bytes(bytes).map(|pk| pk.prepare())
+    }
+
+    /// The entanglement tables precomputed for the public key.
+    pub fn tables(&self) -> &EntangleTables {
+        &self.tables
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl EntangleTables {
+    /// Entangle an identity with the parameters of the public key, i.e. `u' + sum(u[i] * v[i])`.
+    ///
+    /// Gives the same result as `PublicKey::entangle_identity`.
+    pub fn entangle_identity(&self, v: &Identity) -> G1Affine {
+        entangle_windowed(self, v).into()
+    }
+
+    /// Size in bytes of the tables in memory, including their heap allocation.
+    pub fn memory_size(&self) -> usize {
+        core::mem::size_of::<Self>()
+            + self.tables.capacity() * core::mem::size_of::<[G1Affine; 1 << WINDOW]>()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl EncryptCache {
+    /// Prepare the public key, and cache up to `capacity` prepared identities.
+    pub fn new(pk: &PublicKey, capacity: usize) -> Self {
+        EncryptCache {
+            ppk: pk.prepare(),
+            capacity,
+            tick: 0,
+            entries: alloc::collections::BTreeMap::new(),
+        }
+    }
+
+    /// Encrypt a message for an identity, preparing the identity only if it is not cached.
+    ///
+    /// Gives the same result as `encrypt` with the same `rng`.
+    pub fn encrypt<R: RngCore + CryptoRng>(
+        &mut self,
+        v: &Identity,
+        m: &Message,
+        rng: &mut R,
+    ) -> CipherText {
+        let pid = self.prepared_identity(v);
+        encrypt_prepared_identity(&self.ppk, &pid, m, rng)
+    }
+
+    fn prepared_identity(&mut self, v: &Identity) -> PreparedIdentity {
+        self.tick += 1;
+        if let Some((pid, used)) = self.entries.get_mut(v) {
+            *used = self.tick;
+            return *pid;
+        }
+
+        let pid = self.ppk.pk.prepare_identity(v);
+        if self.capacity == 0 {
+            return pid;
+        }
+        if self.entries.len() >= self.capacity {
+            let lru = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(id, _)| *id);
+            if let Some(lru) = lru {
+                self.entries.remove(&lru);
+            }
+        }
+        self.entries.insert(*v, (pid, self.tick));
+        pid
+    }
+
+    /// The prepared public key used for encryption.
+    pub fn prepared_public_key(&self) -> &PreparedPublicKey {
+        &self.ppk
+    }
+
+    /// The maximum number of cached identities.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of currently cached identities.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no identities are cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether an identity is currently cached.
+    pub fn contains(&self, v: &Identity) -> bool {
+        self.entries.contains_key(v)
+    }
+}
+
+impl ConditionallySelectable for PublicKey {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        PublicKey {
+            g: G2Affine::conditional_select(&a.g, &b.g, choice),
+            g1: G1Affine::conditional_select(&a.g1, &b.g1, choice),
+            g2: G2Affine::conditional_select(&a.g2, &b.g2, choice),
+            uprime: G1Affine::conditional_select(&a.uprime, &b.uprime, choice),
+            u: Parameters::conditional_select(&a.u, &b.u, choice),
+        }
+    }
+}
+
+impl ConditionallySelectable for Message {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Message(Gt::conditional_select(&a.0, &b.0, choice))
+    }
+}
+
+impl Eq for CipherText {}
+
+impl PartialOrd for CipherText {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CipherText {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.to_bytes().cmp(&other.to_bytes())
+    }
+}
+
+impl core::hash::Hash for CipherText {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state);
+    }
+}
+
+impl ConditionallySelectable for CipherText {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        CipherText {
+            c1: Gt::conditional_select(&a.c1, &b.c1, choice),
+            c2: G2Affine::conditional_select(&a.c2, &b.c2, choice),
+            c3: G1Affine::conditional_select(&a.c3, &b.c3, choice),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<PublicKey> for PreparedPublicKey {
+    fn from(pk: PublicKey) -> Self {
+        pk.prepare()
+    }
+}
+
+/// Compares the top-level points before the parameters, and stops at the first difference.
+///
+/// Public keys are not secret, so this comparison is not constant time.
+impl PartialEq for PublicKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.g == other.g
+            && self.g1 == other.g1
+            && self.g2 == other.g2
+            && self.uprime == other.uprime
+            && self.u == other.u
+    }
+}
+
+impl ConstantTimeEq for SecretKey {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.g1prime.ct_eq(&other.g1prime)
+    }
+}
+
+impl PartialEq for SecretKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl ConstantTimeEq for UserSecretKey {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.d1.ct_eq(&other.d1) & self.d2.ct_eq(&other.d2)
+    }
+}
+
+impl PartialEq for UserSecretKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl SecretKey {
+    /// Split the secret key into `n` additive shares, such that each can be held by a different party.
+    ///
+    /// Any `n - 1` shares reveal nothing about the secret key, but all `n` are required
+    /// to extract user secret keys using `extract_partial_usk` and `UserSecretKey::combine`.
+    ///
+    /// # Panics
+    /// If `n` is zero.
+    #[cfg(feature = "alloc")]
+    pub fn split<R: RngCore + CryptoRng>(&self, n: usize, rng: &mut R) -> Vec<SecretKeyShare> {
+        assert!(n > 0, "can not split into zero shares");
+
+        let mut rest: G1Projective = self.g1prime.into();
+        let mut shares: Vec<SecretKeyShare> = (1..n)
+            .map(|_| {
+                let g1prime = rand_g1(rng);
+                rest -= g1prime;
+                SecretKeyShare {
+                    g1prime: g1prime.into(),
+                }
+            })
+            .collect();
+        shares.push(SecretKeyShare {
+            g1prime: rest.into(),
+        });
+        shares
+    }
+
+    pub fn to_bytes(&self) -> [u8; SECRET_KEY_BYTES] {
+        self.g1prime.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; SECRET_KEY_BYTES]) -> CtOption<Self> {
+        G1Affine::from_compressed(bytes).map(|g1prime| SecretKey { g1prime })
+    }
+
+    /// Deserialize a secret key, reporting which field failed to decompress.
+    ///
+    /// Unlike `from_bytes`, this function does not run in constant time.
+    pub fn try_from_bytes(bytes: &[u8; SECRET_KEY_BYTES]) -> Result<Self, DeserializeError> {
+        Ok(SecretKey {
+            g1prime: try_g1(bytes, "g1prime")?,
+        })
+    }
+}
+
+impl BlindRequest {
+    pub fn to_bytes(&self) -> [u8; 48] {
+        self.x.to_compressed()
+    }
+
+    /// Deserialize a request, as the PKG.
+    ///
+    /// Rejects the identity point, as responding to it would reveal the secret key.
+    pub fn from_bytes(bytes: &[u8; 48]) -> CtOption<Self> {
+        G1Affine::from_compressed(bytes)
+            .and_then(|x| CtOption::new(BlindRequest { x }, !x.is_identity()))
+    }
+}
+
+impl BlindResponse {
+    pub fn to_bytes(&self) -> [u8; USER_SECRET_KEY_BYTES] {
+        let mut res = [0u8; USER_SECRET_KEY_BYTES];
+        let (d1, d2) = mut_array_refs![&mut res, 48, 96];
+        *d1 = self.d1.to_compressed();
+        *d2 = self.d2.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; USER_SECRET_KEY_BYTES]) -> CtOption<Self> {
+        let (d1, d2) = array_refs![bytes, 48, 96];
+
+        let d1 = G1Affine::from_compressed(d1);
+        let d2 = G2Affine::from_compressed(d2);
+
+        d1.and_then(|d1| d2.map(|d2| BlindResponse { d1, d2 }))
+    }
+}
+
+impl CompactPublicKey {
+    /// Decompress all parameters, for faster encryption.
+    pub fn decompress(&self) -> PublicKey {
+        PublicKey {
+            g: self.g,
+            g1: self.g1,
+            g2: self.g2,
+            uprime: self.uprime,
+            u: Parameters::from_bytes(&self.u).unwrap(),
+        }
+    }
+
+    /// Serialize to the same format as `PublicKey::to_bytes`.
+    pub fn to_bytes(&self) -> [u8; PUBLIC_KEY_BYTES] {
+        let mut res = [0u8; PUBLIC_KEY_BYTES];
+        let (g, g1, g2, uprime, u) = mut_array_refs![&mut res, 96, 48, 96, 48, PARAMETERSIZE];
+        *g = self.g.to_compressed();
+        *g1 = self.g1.to_compressed();
+        *g2 = self.g2.to_compressed();
+        *uprime = self.uprime.to_compressed();
+        *u = self.u;
+        res
+    }
+
+    /// Deserialize the same format as `PublicKey::from_bytes`, validating all parameters.
+    pub fn from_bytes(bytes: &[u8; PUBLIC_KEY_BYTES]) -> CtOption<Self> {
+        let u = *array_ref![bytes, PUBLIC_KEY_BYTES - PARAMETERSIZE, PARAMETERSIZE];
+
+        PublicKey::from_bytes(bytes).map(|pk| CompactPublicKey {
+            g: pk.g,
+            g1: pk.g1,
+            g2: pk.g2,
+            uprime: pk.uprime,
+            u,
+        })
+    }
+}
+
+impl SecretKeyShare {
+    pub fn to_bytes(&self) -> [u8; SECRET_KEY_BYTES] {
+        self.g1prime.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; SECRET_KEY_BYTES]) -> CtOption<Self> {
+        G1Affine::from_compressed(bytes).map(|g1prime| SecretKeyShare { g1prime })
+    }
+}
+
+impl PartialUserSecretKey {
+    pub fn to_bytes(&self) -> [u8; USER_SECRET_KEY_BYTES] {
+        let mut res = [0u8; USER_SECRET_KEY_BYTES];
+        let (d1, d2) = mut_array_refs![&mut res, 48, 96];
+        *d1 = self.d1.to_compressed();
+        *d2 = self.d2.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; USER_SECRET_KEY_BYTES]) -> CtOption<Self> {
+        let (d1, d2) = array_refs![bytes, 48, 96];
+
+        let d1 = G1Affine::from_compressed(d1);
+        let d2 = G2Affine::from_compressed(d2);
+
+        d1.and_then(|d1| d2.map(|d2| PartialUserSecretKey { d1, d2 }))
+    }
+}
+
+impl UserSecretKey {
+    /// Verify that this user secret key is valid for an identity,
+    /// i.e. e(d1, g) = e(g1, g2) * e(entangle(v), d2).
+    ///
+    /// Protects against a faulty or malicious PKG handing out invalid keys.
+    pub fn verify(&self, pk: &PublicKey, v: &Identity) -> bool {
+        is_valid_usk(pk, v, self).into()
+    }
+
+    /// Derive a fresh, independently distributed user secret key for the same identity,
+    /// without requiring the PKG secret key.
+    ///
+    /// Requires the identity this key was extracted for, to recompute its entanglement
+    /// with the public key. Using another identity results in an invalid key.
+    pub fn rerandomize<R: RngCore + CryptoRng>(
+        &self,
+        pk: &PublicKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> UserSecretKey {
+        let r = rand_secret_scalar(rng);
+        let ucoll = entangle(pk, v);
+        let d1 = (ucoll * *r + self.d1).into();
+        let d2 = (pk.g * *r + self.d2).into();
+
+        UserSecretKey { d1, d2 }
+    }
+
+    /// Select `a` if `choice` is 0, or `b` if `choice` is 1, in constant time.
+    ///
+    /// `UserSecretKey` can not implement `ConditionallySelectable`, as it is not `Copy`.
+    pub fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        UserSecretKey {
+            d1: G1Affine::conditional_select(&a.d1, &b.d1, choice),
+            d2: G2Affine::conditional_select(&a.d2, &b.d2, choice),
+        }
+    }
+
+    /// Combine the contributions of all secret key shares into a user secret key.
+    ///
+    /// Every share returned by `SecretKey::split` must have contributed exactly once,
+    /// for the same identity. Otherwise the result is an invalid key, which `verify` detects.
+    pub fn combine(parts: &[PartialUserSecretKey]) -> UserSecretKey {
+        let mut d1 = G1Projective::identity();
+        let mut d2 = G2Projective::identity();
+        for part in parts.iter() {
+            d1 += part.d1;
+            d2 += part.d2;
+        }
+
+        UserSecretKey {
+            d1: d1.into(),
+            d2: d2.into(),
+        }
+    }
+
+    /// Precompute the Miller loop for `d2`, which is used in every decryption.
+    #[cfg(feature = "alloc")]
+    pub fn prepare(&self) -> PreparedUserSecretKey {
+        PreparedUserSecretKey {
+            d1: self.d1,
+            d2: G2Prepared::from(self.d2),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; USER_SECRET_KEY_BYTES] {
+        let mut res = [0u8; USER_SECRET_KEY_BYTES];
+        let (d1, d2) = mut_array_refs![&mut res, 48, 96];
+        *d1 = self.d1.to_compressed();
+        *d2 = self.d2.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; USER_SECRET_KEY_BYTES]) -> CtOption<Self> {
+        let (d1, d2) = array_refs![bytes, 48, 96];
+
+        let d1 = G1Affine::from_compressed(d1);
+        let d2 = G2Affine::from_compressed(d2);
+
+        d1.and_then(|d1| d2.map(|d2| UserSecretKey { d1, d2 }))
+    }
+
+    /// Deserialize a user secret key, reporting which field failed to decompress.
+    ///
+    /// Unlike `from_bytes`, this function does not run in constant time.
+    pub fn try_from_bytes(bytes: &[u8; USER_SECRET_KEY_BYTES]) -> Result<Self, DeserializeError> {
+        let (d1, d2) = array_refs![bytes, 48, 96];
+
+        Ok(UserSecretKey {
+            d1: try_g1(d1, "d1")?,
+            d2: try_g2(d2, "d2")?,
+        })
+    }
+}
+
+/// Formats bytes as lowercase hex in `Debug` output.
+struct Hex<'a>(&'a [u8]);
+
+impl core::fmt::Debug for Hex<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for b in self.0.iter() {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl core::fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("PublicKey")
+            .field("fingerprint", &Hex(&self.fingerprint()[..8]))
+            .field("parameters", &CHUNKS)
+            .finish_non_exhaustive()
+    }
+}
+
+impl core::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("SecretKey(<redacted>)")
+    }
+}
+
+impl core::fmt::Debug for SecretKeyShare {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("SecretKeyShare(<redacted>)")
+    }
+}
+
+impl core::fmt::Debug for UserSecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("UserSecretKey(<redacted>)")
+    }
+}
+
+impl core::fmt::Debug for PartialUserSecretKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("PartialUserSecretKey(<redacted>)")
+    }
+}
+
+impl core::fmt::Debug for BlindState {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("BlindState(<redacted>)")
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for SecretKey {
+    fn zeroize(&mut self) {
+        self.g1prime.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for SecretKey {}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for UserSecretKey {
+    fn zeroize(&mut self) {
+        self.d1.zeroize();
+        self.d2.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for UserSecretKey {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for UserSecretKey {}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for SecretKeyShare {
+    fn zeroize(&mut self) {
+        self.g1prime.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for SecretKeyShare {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for SecretKeyShare {}
+
+impl Message {
+    /// Generate a random point on the paired curve.
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self(rand_gt(rng))
+    }
+
+    /// Generate `n` random points on the paired curve, for example for a pool of KEM secrets.
+    ///
+    /// Gives the same result as calling `generate` `n` times with the same `rng`,
+    /// but computes the pairing of the generators only once.
+    #[cfg(feature = "alloc")]
+    pub fn generate_many<R: RngCore + CryptoRng>(rng: &mut R, n: usize) -> Vec<Self> {
+        let generator = irmaseal_curve::pairing(&G1Affine::generator(), &G2Affine::generator());
+
+        (0..n)
+            .map(|_| Message(generator * rand_scalar(rng)))
+            .collect()
+    }
+
+    /// Check in constant time whether this message is the identity element of the paired group.
+    ///
+    /// A random or correctly decrypted message is the identity only with negligible probability,
+    /// so this indicates an error or a crafted ciphertext. No key should be derived from it.
+    pub fn is_identity(&self) -> Choice {
+        self.0.ct_eq(&Gt::identity())
+    }
+
+    /// Combine two messages using the group operation of the paired group.
+    ///
+    /// Exposes the homomorphic structure of the scheme: adding a message to the `c1` component of
+    /// a ciphertext for `m` yields a ciphertext for `m.combine(other)`. This is useful for masking
+    /// in protocols built on top of the scheme, but it also means ciphertexts are malleable,
+    /// so a combined message should only be used if the protocol accounts for that.
+    /// A combination involving a known message is not random, so do not derive keys from it
+    /// unless at least one of the combined messages is secret and uniformly random.
+    pub fn combine(&self, other: &Message) -> Message {
+        Message(self.0 + other.0)
+    }
+
+    /// Wrap an element of the paired group as a message.
+    pub fn from_gt(gt: Gt) -> Message {
+        Message(gt)
+    }
+
+    /// The element of the paired group this message wraps, see `combine` for the caveats.
+    pub fn as_gt(&self) -> &Gt {
+        &self.0
+    }
+
+    /// Derive an `N`-byte symmetric key from this message for the context described by `info`.
+    ///
+    /// Hashes a domain separation tag, the compressed message and `info` with SHAKE256.
+    /// Different `info` strings give independent keys from the same message.
+    pub fn derive_key<const N: usize>(&self, info: &[u8]) -> [u8; N] {
+        shake256_dst(DERIVE_KEY_DST, &[&self.to_bytes(), info])
+    }
+
+    /// Derive a 32-byte symmetric key, such as an AES-256 key, from this message.
+    ///
+    /// Hashes the compressed message with a domain-separated sha3-256.
+    /// Also available as `From<Message> for [u8; 32]`.
+    pub fn into_aes_key(self) -> [u8; 32] {
+        sha3_256_dst(AES_KEY_DST, &[&self.to_bytes()])
+    }
+
+    /// The compressed representation of the message.
+    ///
+    /// This is not uniformly random and must not be used as a key directly,
+    /// use `into_aes_key` or `derive_key` instead.
+    pub fn to_bytes(&self) -> [u8; MESSAGE_BYTES] {
+        self.0.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; MESSAGE_BYTES]) -> CtOption<Self> {
+        Gt::from_compressed(bytes).map(Message)
+    }
+
+    /// Deserialize a message, reporting an error if it failed to decompress.
+    ///
+    /// Unlike `from_bytes`, this function does not run in constant time.
+    pub fn try_from_bytes(bytes: &[u8; MESSAGE_BYTES]) -> Result<Self, DeserializeError> {
+        try_gt(bytes, "m").map(Message)
+    }
+}
+
+impl crate::scheme::MessageBytes for Message {
+    type Bytes = [u8; MESSAGE_BYTES];
+
+    fn to_bytes(&self) -> [u8; MESSAGE_BYTES] {
+        Message::to_bytes(self)
+    }
+
+    fn into_aes_key(self) -> [u8; 32] {
+        Message::into_aes_key(self)
+    }
+}
+
+impl From<Message> for [u8; 32] {
+    fn from(m: Message) -> [u8; 32] {
+        m.into_aes_key()
+    }
+}
+
+impl Parameters {
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_bytes(&self) -> [u8; PARAMETERSIZE] {
+        let mut res = [0u8; PARAMETERSIZE];
+        for (i, p) in self.0.iter().enumerate() {
+            *array_mut_ref![&mut res, i * 48, 48] = p.to_compressed();
+        }
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; PARAMETERSIZE]) -> CtOption<Self> {
+        let mut res = [G1Affine::default(); CHUNKS];
+        let mut is_some = Choice::from(1u8);
+        for (i, ri) in res.iter_mut().enumerate() {
+            is_some &= G1Affine::from_compressed(array_ref![bytes, i * 48, 48])
+                .map(|s| {
+                    *ri = s;
+                })
+                .is_some();
+        }
+        CtOption::new(Parameters(res), is_some)
+    }
+}
+
+impl ConditionallySelectable for Parameters {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut res = [G1Affine::default(); CHUNKS];
+        for (i, (ai, bi)) in a.0.iter().zip(b.0.iter()).enumerate() {
+            res[i] = G1Affine::conditional_select(ai, bi, choice);
+        }
+        Parameters(res)
+    }
+}
+
+impl Clone for Parameters {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl Copy for Parameters {}
+
+impl Parameters {
+    fn try_from_bytes(bytes: &[u8; PARAMETERSIZE]) -> Result<Self, DeserializeError> {
+        let mut res = [G1Affine::default(); CHUNKS];
+        for (i, ri) in res.iter_mut().enumerate() {
+            *ri = try_g1(array_ref![bytes, i * 48, 48], "u")?;
+        }
+        Ok(Parameters(res))
+    }
+}
+
+impl PartialEq for Parameters {
+    fn eq(&self, rhs: &Self) -> bool {
+        self.0.iter().zip(rhs.0.iter()).all(|(x, y)| x.eq(y))
+    }
+}
+
+impl Default for Parameters {
+    fn default() -> Self {
+        Parameters([G1Affine::default(); CHUNKS])
+    }
+}
+
+impl Identity {
+    /// Hash a byte slice to a set of Identity parameters, which acts as a user public key.
+    /// Uses sha3-256 internally.
+    ///
+    /// Does not apply domain separation, and is kept for compatibility.
+    /// New applications should prefer `derive_with_domain`.
+    pub fn derive(b: &[u8]) -> Identity {
+        Identity(tiny_keccak::sha3_256(b))
+    }
+
+    /// Hash a byte slice to a set of Identity parameters, separated by an application specific domain.
+    ///
+    /// The domain is prefixed to the input with its length as a 64-bit big-endian integer,
+    /// such that the same bytes in different domains never result in the same identity.
+    /// Note that an empty domain does not give the same result as `derive`.
+    pub fn derive_with_domain(domain: &[u8], b: &[u8]) -> Identity {
+        let mut h = IdentityHasher::with_domain(domain);
+        h.update(b);
+        h.finalize()
+    }
+
+    /// Hash an email address to a set of Identity parameters.
+    ///
+    /// Equivalent to `derive_with_domain(b"ibe-waters-identity-email", email.as_bytes())`.
+    /// The address is hashed as is, so it should be normalized by the caller.
+    pub fn from_email(email: &str) -> Identity {
+        Self::derive_with_domain(EMAIL_DOMAIN, email.as_bytes())
+    }
+
+    /// Hash a phone number to a set of Identity parameters.
+    ///
+    /// Equivalent to `derive_with_domain(b"ibe-waters-identity-phone", phone.as_bytes())`.
+    /// The number is hashed as is, so it should be normalized by the caller, for example to E.164.
+    pub fn from_phone(phone: &str) -> Identity {
+        Self::derive_with_domain(PHONE_DOMAIN, phone.as_bytes())
+    }
+
+    /// Hash the 16 bytes of a UUID to a set of Identity parameters.
+    ///
+    /// Equivalent to `derive_with_domain(b"ibe-waters-identity-uuid", uuid)`.
+    pub fn from_uuid(uuid: &[u8; 16]) -> Identity {
+        Self::derive_with_domain(UUID_DOMAIN, uuid)
+    }
+
+    /// Hash a byte slice together with a validity epoch to a set of Identity parameters.
+    ///
+    /// A user secret key extracted for an identity in one epoch can not decrypt ciphertexts
+    /// encrypted for the same identity in another epoch, so keys expire when the epoch changes.
+    /// The input is hashed in the domain `b"ibe-waters-identity-epoch"`, after the epoch
+    /// as a 64-bit big-endian integer.
+    ///
+    /// The epoch is chosen by the application, for example the number of days or weeks since
+    /// the Unix epoch. Every user needs a new key from the PKG in every epoch, so it should be
+    /// coarse enough to keep the extraction load manageable, and fine enough to bound the
+    /// time a leaked or revoked key remains usable.
+    pub fn derive_with_epoch(b: &[u8], epoch: u64) -> Identity {
+        let mut h = IdentityHasher::with_domain(EPOCH_DOMAIN);
+        h.update(&epoch.to_be_bytes());
+        h.update(b);
+        h.finalize()
+    }
+
+    /// Hash a set of structured claims, such as a role, organisation and expiry,
+    /// to a set of Identity parameters.
+    ///
+    /// The claims are serialized canonically, such that every party constructing the same
+    /// claims gets the same identity. The serialization is hashed in the domain
+    /// `b"ibe-waters-identity-claims"`, and consists of the number of claims, followed by every
+    /// key and value in ascending order of the keys, each prefixed by its length in bytes.
+    /// All numbers are encoded as 64-bit big-endian integers, and keys and values as UTF-8.
+    #[cfg(feature = "alloc")]
+    pub fn derive_claims(
+        claims: &alloc::collections::BTreeMap<alloc::string::String, alloc::string::String>,
+    ) -> Identity {
+        let mut h = IdentityHasher::with_domain(CLAIMS_DOMAIN);
+        h.update(&(claims.len() as u64).to_be_bytes());
+        for (k, v) in claims {
+            h.update(&(k.len() as u64).to_be_bytes());
+            h.update(k.as_bytes());
+            h.update(&(v.len() as u64).to_be_bytes());
+            h.update(v.as_bytes());
+        }
+        h.finalize()
+    }
+
+    /// Hash a byte slice together with a key version to a set of Identity parameters.
+    ///
+    /// Allows revoking the keys of a user without new primitives: the PKG keeps and publishes
+    /// the current version per user, and bumps it when a key of that user is compromised.
+    /// Encryptors fetch the current version and encrypt for the versioned identity, and the PKG
+    /// only extracts keys for the current version, so keys for revoked versions can not decrypt
+    /// new ciphertexts. Ciphertexts encrypted before the revocation remain readable with the old key.
+    ///
+    /// The input is hashed in the domain `b"ibe-waters-identity-version"`, after the version
+    /// as a 32-bit big-endian integer.
+    pub fn derive_versioned(b: &[u8], version: u32) -> Identity {
+        let mut h = IdentityHasher::with_domain(VERSION_DOMAIN);
+        h.update(&version.to_be_bytes());
+        h.update(b);
+        h.finalize()
+    }
+
+    /// Hash a byte slice to a set of Identity parameters using any [RustCrypto](https://github.com/RustCrypto/hashes) digest `D`,
+    /// for interoperability with specifications built around other hash functions.
+    ///
+    /// If the output of `D` is at least `HASH_BYTE_LEN` bytes, the result is its first `HASH_BYTE_LEN` bytes,
+    /// so `derive_with::<sha3::Sha3_256>` is equivalent to `derive`.
+    /// Shorter outputs are expanded to the concatenation of `D([0] || b)`, `D([1] || b)`, ...,
+    /// truncated to `HASH_BYTE_LEN` bytes.
+    pub fn derive_with<D: digest::Digest>(b: &[u8]) -> Identity {
+        let mut res = [0u8; HASH_BYTE_LEN];
+
+        let len = D::output_size();
+        if len >= HASH_BYTE_LEN {
+            res.copy_from_slice(&D::digest(b)[..HASH_BYTE_LEN]);
+        } else {
+            for (i, chunk) in res.chunks_mut(len).enumerate() {
+                let block = D::new().chain([i as u8]).chain(b).finalize();
+                chunk.copy_from_slice(&block[..chunk.len()]);
+            }
+        }
+
+        Identity(res)
+    }
+
+    /// Hash a byte slice to a set of Identity parameters using the SHAKE256 XOF
+    /// instead of sha3-256, for interoperability with other identity encodings.
+    pub fn derive_shake(b: &[u8]) -> Identity {
+        let mut h = tiny_keccak::Keccak::new_shake256();
+        h.update(b);
+
+        let mut res = [0u8; HASH_BYTE_LEN];
+        h.finalize(&mut res);
+        Identity(res)
+    }
+
+    /// Hash a byte slice to a set of Identity parameters using BLAKE3 instead of sha3-256,
+    /// which is faster for large inputs such as documents. Requires the `blake3` feature.
+    ///
+    /// The identity is the 32-byte BLAKE3 digest of the input, without domain separation, so it
+    /// differs from the identity `derive` gives for the same input. The PKG and all encryptors
+    /// of a deployment must use the same hash, or users can not decrypt.
+    #[cfg(feature = "blake3")]
+    pub fn derive_blake3(b: &[u8]) -> Identity {
+        Identity(*blake3::hash(b).as_bytes())
+    }
+
+    /// Hash a string slice to a set of Identity parameters.
+    /// Directly converts characters to UTF-8 byte representation.
+    pub fn derive_str(s: &str) -> Identity {
+        Self::derive(s.as_bytes())
+    }
+
+    /// Create an Identity directly from a precomputed digest of `HASH_BYTE_LEN` bytes,
+    /// for example when a PKG receives digests computed by an upstream service.
+    ///
+    /// No hashing is applied. Each bit of the digest selects one of the public key parameters,
+    /// so the digest should be the output of a collision resistant hash function, and the caller
+    /// is responsible for domain separation. To interoperate with `derive`, compute the sha3-256
+    /// of the identity bytes.
+    pub fn from_hash_bytes(bytes: &[u8; HASH_BYTE_LEN]) -> Identity {
+        Identity(*bytes)
+    }
+
+    /// A copy of the digest this Identity is built from, see `as_bytes`.
+    pub fn to_hash_bytes(&self) -> [u8; HASH_BYTE_LEN] {
+        *self.as_bytes()
+    }
+
+    /// Map the digest of this Identity into the scalar field, for protocols built on top of it,
+    /// such as Boneh-Boyen-style schemes or commitments to the identity.
+    ///
+    /// Uses `hash_to_field` of [RFC 9380](https://www.rfc-editor.org/rfc/rfc9380.html#section-5.2)
+    /// with `expand_message_xof` using SHAKE256, `k = 128` and the domain separation tag
+    /// `ibe-waters-identity-scalar`, hashing the 32-byte digest. Runs in constant time.
+    pub fn to_scalar(&self) -> Scalar {
+        hash_to_scalar(&self.0, SCALAR_DST)
+    }
+
+    /// A reference to the digest this Identity is built from, for example to index by it.
+    pub fn as_bytes(&self) -> &[u8; HASH_BYTE_LEN] {
+        &self.0
+    }
+}
+
+/// Same as `Identity::from_hash_bytes`: the bytes are used as the digest directly, without hashing.
+impl From<[u8; HASH_BYTE_LEN]> for Identity {
+    fn from(bytes: [u8; HASH_BYTE_LEN]) -> Identity {
+        Identity(bytes)
+    }
+}
+
+impl AsRef<[u8]> for Identity {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl IdentityHasher {
+    /// Start hashing an identity, equivalent to `Identity::derive`.
+    pub fn new() -> Self {
+        IdentityHasher(tiny_keccak::Keccak::new_sha3_256())
+    }
+
+    /// Start hashing an identity in a domain, equivalent to `Identity::derive_with_domain`.
+    pub fn with_domain(domain: &[u8]) -> Self {
+        let mut h = Self::new();
+        absorb_dst(&mut h.0, domain);
+        h
+    }
+
+    /// Feed the next part of the identity to the hasher.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Finish hashing, returning the Identity.
+    pub fn finalize(self) -> Identity {
+        let mut res = [0u8; HASH_BYTE_LEN];
+        self.0.finalize(&mut res);
+        Identity(res)
+    }
+}
+
+impl Default for IdentityHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CipherText {
+    /// Check without a user secret key that this ciphertext is well-formed for an identity,
+    /// i.e. e(c3, g) = e(entangle(v), c2), for example for a relay to reject junk before storing it.
+    ///
+    /// Tampering with `c1` can not be detected, as it only masks the message.
+    /// Same check as `decrypt_checked`, but not constant time.
+    pub fn is_well_formed(&self, pk: &PublicKey, v: &Identity) -> bool {
+        is_consistent(pk, v, self).into()
+    }
+
+    /// The components `(c1, c2, c3)` of the ciphertext, for example to prove that it was formed correctly.
+    pub fn components(&self) -> (&Gt, &G2Affine, &G1Affine) {
+        (&self.c1, &self.c2, &self.c3)
+    }
+
+    /// Construct a ciphertext from its components `(c1, c2, c3)`.
+    ///
+    /// The components are not checked, so the result only decrypts correctly
+    /// if they were formed as in `encrypt`.
+    pub fn from_components(c1: Gt, c2: G2Affine, c3: G1Affine) -> CipherText {
+        CipherText { c1, c2, c3 }
+    }
+
+    /// Precompute the Miller loop for `c2`, which is used in every decryption attempt.
+    #[cfg(feature = "alloc")]
+    pub fn prepare(&self) -> PreparedCipherText {
+        PreparedCipherText {
+            c1: self.c1,
+            c2: G2Prepared::from(self.c2),
+            c3: self.c3,
+        }
+    }
+
+    /// Derive a ciphertext that decrypts to the same message, but is unlinkable to this one.
+    ///
+    /// Requires the identity this ciphertext was encrypted for, to recompute its entanglement
+    /// with the public key. Using another identity results in a ciphertext that can not be decrypted.
+    pub fn rerandomize<R: RngCore + CryptoRng>(
+        &self,
+        pk: &PublicKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> CipherText {
+        let t = rand_secret_scalar(rng);
+        let c3coll = entangle(pk, v);
+
+        let c1 = irmaseal_curve::pairing(&pk.g1, &pk.g2) * *t + self.c1;
+        let c2 = (pk.g * *t + self.c2).into();
+        let c3 = (c3coll * *t + self.c3).into();
+
+        CipherText { c1, c2, c3 }
+    }
+
+    pub fn to_bytes(&self) -> [u8; CIPHERTEXT_BYTES] {
+        let mut res = [0u8; CIPHERTEXT_BYTES];
+        let (c1, c2, c3) = mut_array_refs![&mut res, 288, 96, 48];
+        *c1 = self.c1.to_compressed();
+        *c2 = self.c2.to_compressed();
+        *c3 = self.c3.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; CIPHERTEXT_BYTES]) -> CtOption<Self> {
+        let (c1, c2, c3) = array_refs![bytes, 288, 96, 48];
+
+        let c1 = Gt::from_compressed(c1);
+        let c2 = G2Affine::from_compressed(c2);
+        let c3 = G1Affine::from_compressed(c3);
+
+        c1.and_then(|c1| c2.and_then(|c2| c3.map(|c3| CipherText { c1, c2, c3 })))
+    }
+
+    /// Serialize prefixed with `SCHEME_TAG` and `FORMAT_VERSION`, for persistent storage.
+    ///
+    /// Unlike `to_bytes`, future changes to the format can be detected by `from_versioned_bytes`.
+    pub fn to_versioned_bytes(&self) -> [u8; 2 + CIPHERTEXT_BYTES] {
+        to_versioned(&self.to_bytes())
+    }
+
+    /// Deserialize the output of `to_versioned_bytes`, rejecting other schemes and format versions.
+    ///
+    /// Unlike `from_bytes`, this function does not run in constant time.
+    pub fn from_versioned_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        Self::try_from(from_versioned(bytes, CIPHERTEXT_BYTES)?)
+    }
+
+    /// Deserialize a ciphertext, reporting which field failed to decompress.
+    ///
+    /// Unlike `from_bytes`, this function does not run in constant time.
+    pub fn try_from_bytes(bytes: &[u8; CIPHERTEXT_BYTES]) -> Result<Self, DeserializeError> {
+        let (c1, c2, c3) = array_refs![bytes, 288, 96, 48];
+
+        Ok(CipherText {
+            c1: try_gt(c1, "c1")?,
+            c2: try_g2(c2, "c2")?,
+            c3: try_g1(c3, "c3")?,
+        })
+    }
+}
+
+impl KemCipherText {
+    pub fn to_bytes(&self) -> [u8; KEM_CIPHERTEXT_BYTES] {
+        let mut res = [0u8; KEM_CIPHERTEXT_BYTES];
+        let (c2, c3) = mut_array_refs![&mut res, 96, 48];
+        *c2 = self.c2.to_compressed();
+        *c3 = self.c3.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; KEM_CIPHERTEXT_BYTES]) -> CtOption<Self> {
+        let (c2, c3) = array_refs![bytes, 96, 48];
+
+        let c2 = G2Affine::from_compressed(c2);
+        let c3 = G1Affine::from_compressed(c3);
+
+        c2.and_then(|c2| c3.map(|c3| KemCipherText { c2, c3 }))
+    }
+}
+
+impl SealedBox {
+    pub fn to_bytes(&self) -> [u8; SEALED_BOX_BYTES] {
+        let mut res = [0u8; SEALED_BOX_BYTES];
+        let (c, payload, tag) = mut_array_refs![&mut res, KEM_CIPHERTEXT_BYTES, 32, 32];
+        *c = self.c.to_bytes();
+        *payload = self.payload;
+        *tag = self.tag;
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; SEALED_BOX_BYTES]) -> CtOption<Self> {
+        let (c2, c3, payload, tag) = array_refs![bytes, 96, 48, 32, 32];
+
+        let c2 = G2Affine::from_compressed(c2);
+        let c3 = G1Affine::from_compressed(c3);
+
+        c2.and_then(|c2| {
+            c3.map(|c3| SealedBox {
+                c: KemCipherText { c2, c3 },
+                payload: *payload,
+                tag: *tag,
+            })
+        })
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl MultiCipherText {
+    /// The number of identities this ciphertext is encrypted for.
+    pub fn len(&self) -> usize {
+        self.c3.len()
+    }
+
+    /// Whether this ciphertext is encrypted for no identities at all.
+    pub fn is_empty(&self) -> bool {
+        self.c3.is_empty()
+    }
+
+    /// The ciphertext for the identity at `index`, or `None` if the index is out of bounds.
+    pub fn get(&self, index: usize) -> Option<CipherText> {
+        self.c3.get(index).map(|c3| CipherText {
+            c1: self.c1,
+            c2: self.c2,
+            c3: *c3,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = Vec::with_capacity(288 + 96 + 48 * self.c3.len());
+        res.extend_from_slice(&self.c1.to_compressed());
+        res.extend_from_slice(&self.c2.to_compressed());
+        for c3 in self.c3.iter() {
+            res.extend_from_slice(&c3.to_compressed());
+        }
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> CtOption<Self> {
+        if bytes.len() < 288 + 96 || (bytes.len() - 288 - 96) % 48 != 0 {
+            let res = MultiCipherText {
+                c1: Gt::default(),
+                c2: G2Affine::default(),
+                c3: Vec::new(),
+            };
+            return CtOption::new(res, Choice::from(0u8));
+        }
+
+        let (c1, rest) = bytes.split_at(288);
+        let (c2, c3s) = rest.split_at(96);
+
+        let c1 = Gt::from_compressed(array_ref![c1, 0, 288]);
+        let c2 = G2Affine::from_compressed(array_ref![c2, 0, 96]);
+
+        let mut is_some = c1.is_some() & c2.is_some();
+        let mut c3 = Vec::with_capacity(c3s.len() / 48);
+        for chunk in c3s.chunks_exact(48) {
+            let p = G1Affine::from_compressed(array_ref![chunk, 0, 48]);
+            is_some &= p.is_some();
+            c3.push(p.unwrap_or(G1Affine::default()));
+        }
+
+        let res = MultiCipherText {
+            c1: c1.unwrap_or(Gt::default()),
+            c2: c2.unwrap_or(G2Affine::default()),
+            c3,
+        };
+        CtOption::new(res, is_some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    #[allow(dead_code)]
+    struct DefaultSubResults {
+        kid: Identity,
+        m: Message,
+        pk: PublicKey,
+        sk: SecretKey,
+        usk: UserSecretKey,
+        c: CipherText,
+    }
+
+    fn perform_default() -> DefaultSubResults {
+        let mut rng = rand::thread_rng();
+
+        let id = ID.as_bytes();
+        let kid = Identity::derive(id);
+
+        let m = Message::generate(&mut rng);
+
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let c = encrypt(&pk, &kid, &m, &mut rng);
+
+        DefaultSubResults {
+            kid,
+            m,
+            pk,
+            sk,
+            usk,
+            c,
+        }
+    }
+
+    #[test]
+    fn entangle_all_identity_bits() {
+        let (pk, _) = setup(&mut rand::thread_rng());
+
+        // Identities differing only in the first byte of their digest, which used to be ignored.
+        let mut digest = [0u8; HASH_BYTE_LEN];
+        let a = entangle(&pk, &Identity(digest));
+        digest[0] = 0x80;
+        let b = entangle(&pk, &Identity(digest));
+
+        assert_eq!(bits(&digest).count(), HASH_BIT_LEN);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt() {
+        let results = perform_default();
+        let m2 = decrypt(&results.usk, &results.c);
+
+        assert_eq!(results.m, m2);
+    }
+
+    #[test]
+    fn aes_key_vector() {
+        let m = Message(irmaseal_curve::pairing(
+            &G1Affine::generator(),
+            &G2Affine::generator(),
+        ));
+        let key: [u8; 32] = m.into();
+
+        assert_eq!(key, m.into_aes_key());
+        assert_eq!(
+            key,
+            [
+                0xec, 0x15, 0xad, 0x3b, 0xf4, 0xe0, 0x70, 0xb7, 0x07, 0xa6, 0x48, 0xf7, 0x66, 0xd1,
+                0x33, 0xee, 0x8a, 0x0c, 0x4c, 0x4e, 0xc1, 0xe5, 0x82, 0xfc, 0xb1, 0xfd, 0x49, 0xce,
+                0x46, 0x32, 0x6d, 0x09,
+            ]
+        );
+    }
+
+    #[test]
+    fn derive_key() {
+        let results = perform_default();
+        let m2 = decrypt(&results.usk, &results.c);
+
+        let k: [u8; 32] = results.m.derive_key(b"aes-256");
+        assert_eq!(k, m2.derive_key(b"aes-256"));
+        assert_ne!(k, m2.derive_key(b"aes-256-other"));
+        assert_eq!(k[..16], results.m.derive_key::<64>(b"aes-256")[..16]);
+    }
+
+    #[test]
+    fn verify_usk() {
+        let results = perform_default();
+
+        assert!(results.usk.verify(&results.pk, &results.kid));
+        assert!(!results
+            .usk
+            .verify(&results.pk, &Identity::derive_str("email:someone@else.nl")));
+
+        let mut usk = results.usk.clone();
+        usk.d1 = (G1Projective::generator() + usk.d1).into();
+        assert!(!usk.verify(&results.pk, &results.kid));
+    }
+
+    #[test]
+    fn ct_eq_secrets() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let usk2 = extract_usk(&results.pk, &results.sk, &results.kid, &mut rng);
+
+        assert!(bool::from(results.sk.ct_eq(&results.sk.clone())));
+        assert!(bool::from(results.usk.ct_eq(&results.usk.clone())));
+        assert!(!bool::from(results.usk.ct_eq(&usk2)));
+        assert_ne!(results.usk, usk2);
+    }
+
+    #[test]
+    fn setup_from_seed_deterministic() {
+        let (pk, sk) = setup_from_seed(&[7u8; 32]);
+        let (pk2, sk2) = setup_from_seed(&[7u8; 32]);
+        let (pk3, sk3) = setup_from_seed(&[8u8; 32]);
+
+        assert!(pk == pk2 && sk == sk2);
+        assert!(pk != pk3 && sk != sk3);
+    }
+
+    #[test]
+    fn rerandomize_usk() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let usk = results.usk.rerandomize(&results.pk, &results.kid, &mut rng);
+
+        assert_ne!(usk, results.usk);
+        assert_eq!(results.m, decrypt(&usk, &results.c));
+    }
+
+    #[test]
+    fn rerandomize_ciphertext() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let c = results.c.rerandomize(&results.pk, &results.kid, &mut rng);
+
+        assert_ne!(c.to_bytes()[..], results.c.to_bytes()[..]);
+        assert_eq!(results.m, decrypt(&results.usk, &c));
+    }
+
+    #[test]
+    fn eq_encapsulate_decapsulate() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let (c, k) = encapsulate(&results.pk, &results.kid, &mut rng);
+        let k2 = decapsulate(&results.usk, &c);
+
+        assert_eq!(k, k2);
+    }
+
+    #[test]
+    fn eq_encrypt_with_secret_decrypt() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let (c, m) = encrypt_with_secret(&results.pk, &results.kid, &mut rng);
+        let (c2, m2) = encrypt_with_secret(&results.pk, &results.kid, &mut rng);
+
+        assert_eq!(m, decrypt(&results.usk, &c));
+        assert_eq!(m2, decrypt(&results.usk, &c2));
+        assert_ne!(m, m2);
+    }
+
+    #[test]
+    fn decrypt_checked_rejects_tampering() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let m2 = decrypt_checked(&results.pk, &results.usk, &results.kid, &results.c);
+        assert_eq!(results.m, m2.unwrap());
+
+        let other = Identity::derive_str("email:someone@else.nl");
+        let c = encrypt(&results.pk, &other, &results.m, &mut rng);
+        let m3 = decrypt_checked(&results.pk, &results.usk, &results.kid, &c);
+        assert!(bool::from(m3.is_none()));
+
+        let (c, k) = encapsulate(&results.pk, &results.kid, &mut rng);
+        let k2 = decrypt_to_shared_key(&results.pk, &results.usk, &results.kid, &c);
+        assert_eq!(k, k2.unwrap());
+    }
+
+    #[test]
+    fn try_decrypt_any_finds_key() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let other = Identity::derive_str("email:someone@else.nl");
+        let other_usk = extract_usk(&results.pk, &results.sk, &other, &mut rng);
+
+        let ids = [other, results.kid];
+        let usks = [other_usk, results.usk.clone()];
+
+        assert_eq!(
+            try_decrypt_any(&results.pk, &ids, &usks, &results.c),
+            Some((1, results.m))
+        );
+        assert_eq!(
+            try_decrypt_any(&results.pk, &ids[..1], &usks[..1], &results.c),
+            None
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn try_decrypt_any_length_mismatch() {
+        let results = perform_default();
+
+        let ids = [Identity::derive_str("email:someone@else.nl"), results.kid];
+        try_decrypt_any(
+            &results.pk,
+            &ids,
+            core::slice::from_ref(&results.usk),
+            &results.c,
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn eq_encrypt_prepared_decrypt() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let ppk = results.pk.prepare();
+        let c = encrypt_prepared(&ppk, &results.kid, &results.m, &mut rng);
+
+        assert_eq!(results.m, decrypt(&results.usk, &c));
+        assert!(ppk == PreparedPublicKey::from_bytes(&ppk.to_bytes()).unwrap());
+
+        let other = Identity::derive_str("email:someone@else.nl");
+        for v in [results.kid, other].iter() {
+            assert_eq!(entangle(&results.pk, v), entangle_windowed(&ppk.tables, v));
+        }
+        assert_ne!(
+            entangle(&results.pk, &results.kid),
+            entangle(&results.pk, &other)
+        );
+
+        let pusk = results.usk.prepare();
+        assert_eq!(results.m, decrypt_prepared(&pusk, &results.c));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn eq_decrypt_prepared_ciphertext() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let other = Identity::derive_str("email:someone@else.nl");
+        let other_usk = extract_usk(&results.pk, &results.sk, &other, &mut rng);
+
+        let pc = results.c.prepare();
+        assert_eq!(results.m, decrypt_prepared_ciphertext(&results.usk, &pc));
+        assert_ne!(results.m, decrypt_prepared_ciphertext(&other_usk, &pc));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn eq_batch_loop() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let results = perform_default();
+        let ids = [
+            results.kid,
+            Identity::derive_str("email:someone@else.nl"),
+            Identity::derive_str("email:another@one.nl"),
+        ];
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let usks = extract_usk_batch(&results.pk, &results.sk, &ids, &mut rng);
+
+        let mut rng = StdRng::seed_from_u64(42);
+        for (v, usk) in ids.iter().zip(usks.iter()) {
+            assert_eq!(&extract_usk(&results.pk, &results.sk, v, &mut rng), usk);
+        }
+        assert_eq!(results.m, decrypt(&usks[0], &results.c));
+
+        let items = [(ids[1], results.m), (ids[2], Message::generate(&mut rng))];
+        let mut rng = StdRng::seed_from_u64(7);
+        let cs = encrypt_batch(&results.pk, &items, &mut rng);
+
+        let mut rng = StdRng::seed_from_u64(7);
+        for ((v, m), c) in items.iter().zip(cs.iter()) {
+            assert_eq!(&encrypt(&results.pk, v, m, &mut rng), c);
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn eq_encrypt_batch_par_decrypt() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let other = Identity::derive_str("email:someone@else.nl");
+        let other_usk = extract_usk(&results.pk, &results.sk, &other, &mut rng);
+
+        let cs = encrypt_batch_par(&results.pk, &[results.kid, other], &results.m, &mut rng);
+
+        assert_eq!(results.m, decrypt(&results.usk, &cs[0]));
+        assert_eq!(results.m, decrypt(&other_usk, &cs[1]));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn eq_encrypt_decrypt_multi() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let other = Identity::derive_str("email:someone@else.nl");
+        let other_usk = extract_usk(&results.pk, &results.sk, &other, &mut rng);
+
+        let mc = encrypt_multi(&results.pk, &[other, results.kid], &results.m, &mut rng);
+
+        assert_eq!(mc.len(), 2);
+        assert_eq!(decrypt_multi(&other_usk, &mc, 0), Some(results.m));
+        assert_eq!(decrypt_multi(&results.usk, &mc, 1), Some(results.m));
+        assert_ne!(decrypt_multi(&results.usk, &mc, 0), Some(results.m));
+        assert_eq!(decrypt_multi(&results.usk, &mc, 2), None);
+
+        assert_eq!(mc, MultiCipherText::from_bytes(&mc.to_bytes()).unwrap());
+        assert!(bool::from(
+            MultiCipherText::from_bytes(&mc.to_bytes()[1..]).is_none()
+        ));
+    }
+
+    #[test]
+    fn eq_serialize_deserialize() {
+        let result = perform_default();
+
+        assert_eq!(result.m, Message::from_bytes(&result.m.to_bytes()).unwrap());
+        assert!(result.pk == PublicKey::from_bytes(&result.pk.to_bytes()).unwrap());
+        assert_eq!(
+            result.sk,
+            SecretKey::from_bytes(&result.sk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.usk,
+            UserSecretKey::from_bytes(&result.usk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.c,
+            CipherText::from_bytes(&result.c.to_bytes()).unwrap()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn eq_serde_roundtrip() {
+        let result = perform_default();
+
+        let json = serde_json::to_string(&result.pk).unwrap();
+        assert!(result.pk == serde_json::from_str(&json).unwrap());
+        assert_eq!(json, std::format!("\"{}\"", result.pk));
+        let json = serde_json::to_string(&result.usk).unwrap();
+        assert_eq!(result.usk, serde_json::from_str(&json).unwrap());
+        assert_eq!(
+            result.usk,
+            json.trim_matches('"').parse::<UserSecretKey>().unwrap()
+        );
+
+        let bin = bincode::serialize(&result.sk).unwrap();
+        assert_eq!(result.sk, bincode::deserialize(&bin).unwrap());
+        let bin = bincode::serialize(&result.c).unwrap();
+        assert_eq!(result.c, bincode::deserialize(&bin).unwrap());
+
+        let mut bytes = result.c.to_bytes();
+        bytes[288] ^= 0xff;
+        let bin = bincode::serialize(serde_bytes::Bytes::new(&bytes)).unwrap();
+        assert!(bincode::deserialize::<CipherText>(&bin).is_err());
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn zeroize_secrets() {
+        use zeroize::Zeroize;
+
+        let mut result = perform_default();
+
+        result.sk.zeroize();
+        result.usk.zeroize();
+
+        assert_eq!(result.sk.g1prime, G1Affine::default());
+        assert_eq!(result.usk.d1, G1Affine::default());
+        assert_eq!(result.usk.d2, G2Affine::default());
+    }
+
+    #[test]
+    fn validate_public_key() {
+        let results = perform_default();
+
+        assert!(bool::from(results.pk.validate()));
+        assert!(!bool::from(PublicKey::default().validate()));
+
+        let mut pk = results.pk;
+        pk.g2 = G2Affine::identity();
+        assert!(!bool::from(pk.validate()));
+    }
+
+    #[test]
+    fn fingerprint() {
+        let (pk, _) = setup_from_seed(&[0u8; 32]);
+        let (pk2, _) = setup_from_seed(&[1u8; 32]);
+
+        assert_eq!(
+            pk.fingerprint(),
+            PublicKey::from_bytes(&pk.to_bytes()).unwrap().fingerprint()
+        );
+        assert_ne!(pk.fingerprint(), pk2.fingerprint());
+    }
+
+    #[test]
+    fn eq_public_key() {
+        let (pk, _) = setup_from_seed(&[0u8; 32]);
+        let (pk2, _) = setup_from_seed(&[1u8; 32]);
+
+        assert_eq!(pk, PublicKey::from_bytes(&pk.to_bytes()).unwrap());
+        assert_ne!(pk, pk2);
+
+        let mut other = pk;
+        other.u.0[CHUNKS - 1] = G1Affine::generator();
+        assert_ne!(pk, other);
+
+        assert!(pk.eq_fingerprint(&pk.fingerprint()));
+        assert!(!pk.eq_fingerprint(&pk2.fingerprint()));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn fingerprint_hex() {
+        let (pk, _) = setup_from_seed(&[0u8; 32]);
+        let hex = pk.fingerprint_hex();
+
+        assert_eq!(hex.len(), 64);
+        assert!(hex.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')));
+        assert_eq!(&hex[..2], std::format!("{:02x}", pk.fingerprint()[0]));
+    }
+
+    #[test]
+    fn derive_with_domain() {
+        let a = Identity::derive_with_domain(b"app-a", ID.as_bytes());
+        let b = Identity::derive_with_domain(b"app-b", ID.as_bytes());
+
+        assert_eq!(a.0, Identity::derive_with_domain(b"app-a", ID.as_bytes()).0);
+        assert_ne!(a.0, b.0);
+        assert_ne!(
+            Identity::derive_with_domain(b"app-a", b"x").0,
+            Identity::derive_with_domain(b"app-", b"ax").0
+        );
+        assert_ne!(
+            Identity::derive(ID.as_bytes()).0,
+            Identity::derive_with_domain(b"", ID.as_bytes()).0
+        );
+    }
+
+    #[test]
+    fn derive_typed_identities() {
+        let email = Identity::from_email("alice@example.com");
+        assert_eq!(
+            email.0,
+            Identity::derive_with_domain(b"ibe-waters-identity-email", b"alice@example.com").0
+        );
+        assert_ne!(email.0, Identity::from_phone("alice@example.com").0);
+
+        let uuid = [7u8; 16];
+        assert_eq!(
+            Identity::from_uuid(&uuid).0,
+            Identity::derive_with_domain(b"ibe-waters-identity-uuid", &uuid).0
+        );
+        assert_ne!(
+            Identity::from_uuid(&uuid).0,
+            Identity::from_phone(core::str::from_utf8(&uuid).unwrap()).0
+        );
+    }
+
+    #[test]
+    fn identity_as_map_key() {
+        let results = perform_default();
+
+        let mut cache = std::collections::HashMap::new();
+        cache.insert(results.kid, results.usk.clone());
+        cache.insert(Identity::derive_str("email:someone@else.nl"), results.usk);
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(Identity::derive_str(ID), results.kid);
+        assert!(cache.contains_key(&Identity::derive_str(ID)));
+    }
+
+    #[test]
+    fn epoch_scoped_keys() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let now = Identity::derive_with_epoch(ID.as_bytes(), 20_000);
+        let later = Identity::derive_with_epoch(ID.as_bytes(), 20_001);
+        assert_ne!(now, later);
+        assert_ne!(now, results.kid);
+
+        let usk = extract_usk(&results.pk, &results.sk, &now, &mut rng);
+        let c = encrypt(&results.pk, &now, &results.m, &mut rng);
+        assert_eq!(results.m, decrypt(&usk, &c));
+
+        let c = encrypt(&results.pk, &later, &results.m, &mut rng);
+        assert_ne!(results.m, decrypt(&usk, &c));
+    }
+
+    #[test]
+    fn versioned_revocation() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let v1 = Identity::derive_versioned(ID.as_bytes(), 1);
+        let v2 = Identity::derive_versioned(ID.as_bytes(), 2);
+        assert_ne!(v1, v2);
+        assert_ne!(v1, Identity::derive_with_epoch(ID.as_bytes(), 1));
+
+        let revoked = extract_usk(&results.pk, &results.sk, &v1, &mut rng);
+        let current = extract_usk(&results.pk, &results.sk, &v2, &mut rng);
+
+        let c = encrypt(&results.pk, &v2, &results.m, &mut rng);
+        assert_ne!(results.m, decrypt(&revoked, &c));
+        assert_eq!(results.m, decrypt(&current, &c));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn split_extract_combine() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let shares = results.sk.split(3, &mut rng);
+        let parts: Vec<_> = shares
+            .iter()
+            .map(|share| extract_partial_usk(&results.pk, share, &results.kid, &mut rng))
+            .collect();
+
+        let usk = UserSecretKey::combine(&parts);
+        assert!(usk.verify(&results.pk, &results.kid));
+        assert_eq!(results.m, decrypt(&usk, &results.c));
+
+        assert!(!UserSecretKey::combine(&parts[..2]).verify(&results.pk, &results.kid));
+        assert_eq!(
+            shares[0].g1prime,
+            SecretKeyShare::from_bytes(&shares[0].to_bytes())
+                .unwrap()
+                .g1prime
+        );
+        assert_eq!(
+            parts[0],
+            PartialUserSecretKey::from_bytes(&parts[0].to_bytes()).unwrap()
+        );
+    }
+
+    #[test]
+    fn public_key_accessors() {
+        let results = perform_default();
+        let pk = results.pk;
+
+        assert_eq!(pk.g(), pk.g);
+        assert_eq!(pk.g1(), pk.g1);
+        assert_eq!(pk.g2(), pk.g2);
+        assert_eq!(pk.uprime(), pk.uprime);
+
+        // e(d1, g) = e(g1, g2) * e(entangle(v), d2)
+        let lhs = irmaseal_curve::pairing(&results.usk.d1, &pk.g());
+        let rhs = irmaseal_curve::pairing(&pk.g1(), &pk.g2())
+            + irmaseal_curve::pairing(&pk.entangle_identity(&results.kid), &results.usk.d2);
+        assert_eq!(lhs, rhs);
+    }
+
+    #[test]
+    fn blind_extract() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let (req, state) = blind_extract_request(&results.pk, &results.kid, &mut rng);
+        assert_ne!(req.x, results.pk.entangle_identity(&results.kid));
+
+        let req = BlindRequest::from_bytes(&req.to_bytes()).unwrap();
+        let resp = blind_extract_respond(&results.pk, &results.sk, &req, &mut rng);
+        let resp = BlindResponse::from_bytes(&resp.to_bytes()).unwrap();
+
+        let usk = blind_extract_finalize(&state, &resp);
+        assert!(usk.verify(&results.pk, &results.kid));
+        assert_eq!(results.m, decrypt(&usk, &results.c));
+
+        let identity = G1Affine::identity().to_compressed();
+        assert!(bool::from(BlindRequest::from_bytes(&identity).is_none()));
+    }
+
+    #[test]
+    fn debug_output() {
+        let results = perform_default();
+
+        let pk = std::format!("{:?}", results.pk);
+        assert!(pk.starts_with("PublicKey { fingerprint: "));
+        assert!(pk.contains("parameters: 256"));
+        assert!(pk.len() < 100);
+
+        assert_eq!(std::format!("{:?}", results.sk), "SecretKey(<redacted>)");
+        assert_eq!(
+            std::format!("{:?}", results.usk),
+            "UserSecretKey(<redacted>)"
+        );
+
+        let (_, state) = blind_extract_request(&results.pk, &results.kid, &mut rand::thread_rng());
+        assert_eq!(std::format!("{:?}", state), "BlindState(<redacted>)");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn debug_output_shares() {
+        let results = perform_default();
+        let mut rng = rand::thread_rng();
+
+        let shares = results.sk.split(2, &mut rng);
+        let partial = extract_partial_usk(&results.pk, &shares[0], &results.kid, &mut rng);
+        assert_eq!(
+            std::format!("{:?}", shares[0]),
+            "SecretKeyShare(<redacted>)"
+        );
+        assert_eq!(
+            std::format!("{:?}", partial),
+            "PartialUserSecretKey(<redacted>)"
+        );
+    }
+
+    #[test]
+    fn conditional_select() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let c2 = encrypt(&results.pk, &results.kid, &results.m, &mut rng);
+        let usk2 = results.usk.rerandomize(&results.pk, &results.kid, &mut rng);
+
+        for &bit in [0u8, 1].iter() {
+            let choice = Choice::from(bit);
+            let (c, usk) = if bit == 0 {
+                (results.c, &results.usk)
+            } else {
+                (c2, &usk2)
+            };
+
+            assert_eq!(c, CipherText::conditional_select(&results.c, &c2, choice));
+            assert_eq!(
+                usk,
+                &UserSecretKey::conditional_select(&results.usk, &usk2, choice)
+            );
+        }
+    }
+
+    #[test]
+    fn versioned_bytes() {
+        let results = perform_default();
+
+        let pk = results.pk.to_versioned_bytes();
+        assert_eq!(&pk[..2], &[SCHEME_TAG, FORMAT_VERSION]);
+        assert_eq!(results.pk, PublicKey::from_versioned_bytes(&pk).unwrap());
+
+        let mut c = results.c.to_versioned_bytes();
+        assert_eq!(results.c, CipherText::from_versioned_bytes(&c).unwrap());
+        assert_eq!(
+            CipherText::from_versioned_bytes(&c[..1]),
+            Err(ParseError::Length {
+                expected: 2 + CIPHERTEXT_BYTES,
+                found: 1
+            })
+        );
+        assert_eq!(
+            CipherText::from_versioned_bytes(&c[..100]),
+            Err(ParseError::Length {
+                expected: CIPHERTEXT_BYTES,
+                found: 98
+            })
+        );
+
+        c[1] = 2;
+        assert_eq!(
+            CipherText::from_versioned_bytes(&c),
+            Err(ParseError::UnsupportedVersion(2))
+        );
+        c[0] = 7;
+        assert_eq!(
+            CipherText::from_versioned_bytes(&c),
+            Err(ParseError::Scheme(7))
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn generate_many_messages() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let ms = Message::generate_many(&mut StdRng::seed_from_u64(1), 8);
+        assert_eq!(ms.len(), 8);
+        for (i, m) in ms.iter().enumerate() {
+            assert!(ms[i + 1..].iter().all(|m2| m2 != m));
+        }
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let sequential: Vec<_> = (0..8).map(|_| Message::generate(&mut rng)).collect();
+        assert_eq!(ms, sequential);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn eq_encrypt_prepared_identity() {
+        use rand::{rngs::StdRng, SeedableRng};
+        let results = perform_default();
+
+        let ppk = results.pk.prepare();
+        let pid = results.pk.prepare_identity(&results.kid);
+
+        let c = encrypt_prepared_identity(&ppk, &pid, &results.m, &mut StdRng::seed_from_u64(1));
+        let c2 = encrypt(
+            &results.pk,
+            &results.kid,
+            &results.m,
+            &mut StdRng::seed_from_u64(1),
+        );
+        assert_eq!(c, c2);
+        assert_eq!(results.m, decrypt(&results.usk, &c));
+    }
+
+    #[test]
+    fn derive_with_digest() {
+        use sha3::Digest;
+
+        let b = ID.as_bytes();
+        assert_eq!(
+            Identity::derive_with::<sha3::Sha3_256>(b),
+            Identity::derive(b)
+        );
+        assert_eq!(
+            Identity::derive_with::<sha3::Sha3_512>(b).0[..],
+            sha3::Sha3_512::digest(b)[..HASH_BYTE_LEN]
+        );
+
+        let short = Identity::derive_with::<sha3::Sha3_224>(b);
+        let first = sha3::Sha3_224::new().chain([0u8]).chain(b).finalize();
+        let second = sha3::Sha3_224::new().chain([1u8]).chain(b).finalize();
+        assert_eq!(short.0[..28], first[..]);
+        assert_eq!(short.0[28..], second[..4]);
+    }
+
+    #[test]
+    fn eq_encrypt_compact() {
+        use rand::{rngs::StdRng, SeedableRng};
+        let results = perform_default();
+
+        let cpk = results.pk.compress_parameters();
+        assert!(core::mem::size_of::<CompactPublicKey>() < core::mem::size_of::<PublicKey>() / 2);
+        assert!(cpk.decompress() == results.pk);
+        assert!(CompactPublicKey::from_bytes(&results.pk.to_bytes()).unwrap() == cpk);
+        assert_eq!(cpk.to_bytes()[..], results.pk.to_bytes()[..]);
+
+        let c = encrypt_compact(
+            &cpk,
+            &results.kid,
+            &results.m,
+            &mut StdRng::seed_from_u64(1),
+        );
+        let c2 = encrypt(
+            &results.pk,
+            &results.kid,
+            &results.m,
+            &mut StdRng::seed_from_u64(1),
+        );
+        assert_eq!(c, c2);
+        assert_eq!(results.m, decrypt(&results.usk, &c));
+    }
+
+    #[test]
+    fn identity_ordering() {
+        let a = Identity::from_hash_bytes(&[0u8; HASH_BYTE_LEN]);
+        let mut b = [0u8; HASH_BYTE_LEN];
+        b[HASH_BYTE_LEN - 1] = 1;
+        let b = Identity::from_hash_bytes(&b);
+        let c = Identity::from_hash_bytes(&[1u8; HASH_BYTE_LEN]);
+        assert!(a < b && b < c);
+
+        let mut cache = std::collections::BTreeMap::new();
+        for v in [c, a, b].iter() {
+            cache.insert(*v, v.to_hash_bytes());
+        }
+        assert_eq!(
+            cache.keys().copied().collect::<std::vec::Vec<_>>(),
+            [a, b, c]
+        );
+    }
+
+    #[cfg(feature = "test-vectors")]
+    #[test]
+    fn eq_encrypt_with_randomness() {
+        use rand::{rngs::StdRng, SeedableRng};
+        let results = perform_default();
+
+        let t = rand_scalar(&mut StdRng::seed_from_u64(1));
+        let c = encrypt_with_randomness(&results.pk, &results.kid, &results.m, &t);
+        let c2 = encrypt(
+            &results.pk,
+            &results.kid,
+            &results.m,
+            &mut StdRng::seed_from_u64(1),
+        );
+        assert_eq!(c, c2);
+        assert_eq!(
+            c,
+            encrypt_with_randomness(&results.pk, &results.kid, &results.m, &t)
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn derive_claims() {
+        use std::collections::BTreeMap;
+        use std::string::{String, ToString};
+
+        let claims = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+
+        let a = claims(&[("role", "admin"), ("org", "sarif"), ("expiry", "2030")]);
+        let b = claims(&[("expiry", "2030"), ("org", "sarif"), ("role", "admin")]);
+        assert_eq!(Identity::derive_claims(&a), Identity::derive_claims(&b));
+
+        // Moving bytes between a key and a value, or between claims, changes the identity.
+        let c = claims(&[("role", "adminorg"), ("sarif", "")]);
+        let d = claims(&[("rol", "eadmin")]);
+        assert_ne!(
+            Identity::derive_claims(&claims(&[("role", "admin")])),
+            Identity::derive_claims(&d)
+        );
+        assert_ne!(Identity::derive_claims(&a), Identity::derive_claims(&c));
+    }
+
+    #[test]
+    fn identity_as_bytes() {
+        let kid = Identity::derive(ID.as_bytes());
+
+        assert_eq!(kid.as_bytes(), &kid.to_hash_bytes());
+        assert_eq!(kid.as_ref(), &tiny_keccak::sha3_256(ID.as_bytes())[..]);
+        assert_eq!(Identity::from_hash_bytes(kid.as_bytes()), kid);
+    }
+
+    #[test]
+    fn message_is_identity() {
+        let results = perform_default();
+
+        assert!(bool::from(Message(Gt::identity()).is_identity()));
+        assert!(!bool::from(results.m.is_identity()));
+        assert!(!bool::from(decrypt(&results.usk, &results.c).is_identity()));
+    }
+
+    #[test]
+    fn ciphertext_components() {
+        let results = perform_default();
+
+        let (c1, c2, c3) = results.c.components();
+        let c = CipherText::from_components(*c1, *c2, *c3);
+        assert_eq!(c, results.c);
+        assert_eq!(decrypt(&results.usk, &c), results.m);
+    }
+
+    #[test]
+    fn public_key_parameters() {
+        let results = perform_default();
+        let pk = &results.pk;
+
+        assert_eq!(pk.parameters().count(), CHUNKS);
+        assert_eq!(pk.parameter(CHUNKS), None);
+
+        let ucoll = pk
+            .parameters()
+            .zip(bits(results.kid.as_bytes()))
+            .filter(|(_, vi)| bool::from(*vi))
+            .fold(G1Projective::from(pk.uprime()), |acc, (ui, _)| acc + ui);
+        assert_eq!(G1Affine::from(ucoll), pk.entangle_identity(&results.kid));
+        assert_eq!(pk.parameter(3), pk.parameters().nth(3));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn encrypt_cache() {
+        use rand::{rngs::StdRng, SeedableRng};
+        let results = perform_default();
+
+        let a = Identity::derive_str("a");
+        let b = Identity::derive_str("b");
+        let c = Identity::derive_str("c");
+
+        let mut cache = EncryptCache::new(&results.pk, 2);
+        assert!(cache.is_empty());
+
+        let ct = cache.encrypt(&a, &results.m, &mut StdRng::seed_from_u64(1));
+        assert_eq!(
+            ct,
+            encrypt(&results.pk, &a, &results.m, &mut StdRng::seed_from_u64(1))
+        );
+
+        let mut rng = rand::thread_rng();
+        cache.encrypt(&b, &results.m, &mut rng);
+        cache.encrypt(&a, &results.m, &mut rng);
+        cache.encrypt(&c, &results.m, &mut rng);
+
+        // `b` was least recently used, so it was evicted when `c` was added.
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(&a) && cache.contains(&c) && !cache.contains(&b));
+
+        let ct = cache.encrypt(&results.kid, &results.m, &mut rng);
+        assert_eq!(decrypt(&results.usk, &ct), results.m);
+    }
+
+    #[test]
+    fn from_prefix() {
+        let results = perform_default();
+
+        let mut buf = std::vec::Vec::new();
+        buf.extend_from_slice(&results.usk.to_bytes());
+        buf.extend_from_slice(&results.c.to_bytes());
+        buf.extend_from_slice(&results.m.to_bytes());
+
+        let (usk, n) = UserSecretKey::from_prefix(&buf).unwrap();
+        assert_eq!((usk, n), (results.usk, USER_SECRET_KEY_BYTES));
+        let (c, m) = CipherText::from_prefix(&buf[n..]).unwrap();
+        assert_eq!((c, m), (results.c, CIPHERTEXT_BYTES));
+        let (msg, k) = Message::from_prefix(&buf[n + m..]).unwrap();
+        assert_eq!((msg, n + m + k), (results.m, buf.len()));
+
+        assert!(bool::from(
+            Message::from_prefix(&buf[n + m + 1..]).is_none()
+        ));
+        assert!(bool::from(SecretKey::from_prefix(&[]).is_none()));
+        let sk = results.sk.to_bytes();
+        assert_eq!(SecretKey::from_prefix(&sk).unwrap().0, results.sk);
+        let pk = results.pk.to_bytes();
+        assert_eq!(PublicKey::from_prefix(&pk).unwrap().0, results.pk);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    mod prop {
+        use super::*;
+        use proptest::prelude::*;
+        use rand::{rngs::StdRng, SeedableRng};
+
+        fn identity() -> impl Strategy<Value = Identity> {
+            prop_oneof![
+                proptest::collection::vec(any::<u8>(), 0..1024).prop_map(|b| Identity::derive(&b)),
+                any::<[u8; HASH_BYTE_LEN]>().prop_map(|d| Identity::from_hash_bytes(&d)),
+                Just(Identity::from_hash_bytes(&[0u8; HASH_BYTE_LEN])),
+                Just(Identity::from_hash_bytes(&[0xffu8; HASH_BYTE_LEN])),
+            ]
+        }
+
+        proptest! {
+            // Every case runs a setup, which is slow without optimizations.
+            #![proptest_config(ProptestConfig::with_cases(8))]
+
+            #[test]
+            fn encrypt_decrypt(seed in any::<[u8; 32]>(), v in identity(), rng_seed in any::<u64>()) {
+                let mut rng = StdRng::seed_from_u64(rng_seed);
+
+                let (pk, sk) = setup_from_seed(&seed);
+                let usk = extract_usk(&pk, &sk, &v, &mut rng);
+                let m = Message::generate(&mut rng);
+                let c = encrypt(&pk, &v, &m, &mut rng);
+
+                prop_assert_eq!(decrypt(&usk, &c), m);
+                prop_assert!(usk.verify(&pk, &v));
+            }
+
+            #[test]
+            fn serialize_deserialize(seed in any::<[u8; 32]>(), v in identity(), rng_seed in any::<u64>()) {
+                let mut rng = StdRng::seed_from_u64(rng_seed);
+
+                let (pk, sk) = setup_from_seed(&seed);
+                let usk = extract_usk(&pk, &sk, &v, &mut rng);
+                let m = Message::generate(&mut rng);
+                let c = encrypt(&pk, &v, &m, &mut rng);
+
+                prop_assert_eq!(PublicKey::from_bytes(&pk.to_bytes()).unwrap(), pk);
+                prop_assert_eq!(SecretKey::from_bytes(&sk.to_bytes()).unwrap(), sk);
+                prop_assert_eq!(UserSecretKey::from_bytes(&usk.to_bytes()).unwrap(), usk);
+                prop_assert_eq!(Message::from_bytes(&m.to_bytes()).unwrap(), m);
+                prop_assert_eq!(CipherText::from_bytes(&c.to_bytes()).unwrap(), c);
+                prop_assert_eq!(Identity::from_hash_bytes(&v.to_hash_bytes()), v);
+            }
+        }
+    }
+
+    #[test]
+    fn decrypt_for_identity() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let kid2 = Identity::derive_str("email:someone@else.nl");
+        let c2 = encrypt(&results.pk, &kid2, &results.m, &mut rng);
+
+        let inbox = [results.c, c2];
+        let mine: std::vec::Vec<_> = inbox
+            .iter()
+            .filter_map(|c| decrypt_for(&results.pk, &results.usk, &results.kid, c))
+            .collect();
+        assert_eq!(mine, [results.m]);
+        assert_eq!(
+            decrypt_for(&results.pk, &results.usk, &results.kid, &c2),
+            None
+        );
+    }
+
+    #[test]
+    fn encapsulate_decapsulate_kem() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let (c, k) = encapsulate_kem(&results.pk, &results.kid, &mut rng);
+        assert_eq!(k, decapsulate_kem(&results.usk, &c));
+
+        let c = KemCipherText::from_bytes(&c.to_bytes()).unwrap();
+        assert_eq!(k, decapsulate_kem(&results.usk, &c));
+
+        let other = extract_usk(
+            &results.pk,
+            &results.sk,
+            &Identity::derive_str("email:someone@else.nl"),
+            &mut rng,
+        );
+        assert_ne!(k, decapsulate_kem(&other, &c));
     }
-}
 
-impl ConditionallySelectable for Parameters {
-    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
-        let mut res = [G1Affine::default(); CHUNKS];
-        for (i, (ai, bi)) in a.0.iter().zip(b.0.iter()).enumerate() {
-            res[i] = G1Affine::conditional_select(&ai, &bi, choice);
+    #[test]
+    fn dedup_ciphertexts() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let c2 = encrypt(&results.pk, &results.kid, &results.m, &mut rng);
+        let copy = CipherText::from_bytes(&results.c.to_bytes()).unwrap();
+
+        let set: std::collections::HashSet<_> = [results.c, c2, copy].iter().copied().collect();
+        assert_eq!(set.len(), 2);
+        assert_eq!(results.c.cmp(&c2), results.c.to_bytes().cmp(&c2.to_bytes()));
+    }
+
+    #[test]
+    fn public_key_sizes() {
+        let results = perform_default();
+
+        assert_eq!(PublicKey::serialized_len(), results.pk.to_bytes().len());
+        assert!(PublicKey::in_memory_size() > PublicKey::serialized_len());
+        assert!(core::mem::size_of::<CompactPublicKey>() < PublicKey::in_memory_size());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn vec_conversions() {
+        let results = perform_default();
+
+        let bytes = Vec::from(&results.c);
+        assert_eq!(CipherText::try_from(bytes.clone()), Ok(results.c));
+        assert_eq!(
+            UserSecretKey::try_from(Vec::from(&results.usk)).unwrap(),
+            results.usk
+        );
+        assert_eq!(
+            CipherText::try_from(bytes[1..].to_vec()),
+            Err(ParseError::Length {
+                expected: CIPHERTEXT_BYTES,
+                found: CIPHERTEXT_BYTES - 1
+            })
+        );
+    }
+
+    /// Entangle by summing the parameters for the set bits, branching on the identity.
+    fn entangle_reference(pk: &PublicKey, v: &Identity) -> G1Projective {
+        let mut ucoll: G1Projective = pk.uprime.into();
+        for (ui, vi) in pk.u.0.iter().zip(bits(&v.0)) {
+            if bool::from(vi) {
+                ucoll += ui;
+            }
         }
-        Parameters(res)
+        ucoll
     }
-}
 
-impl Clone for Parameters {
-    fn clone(&self) -> Self {
-        let mut res = [G1Affine::default(); CHUNKS];
-        for (src, dst) in self.0.iter().zip(res.as_mut().iter_mut()) {
-            *dst = *src;
+    #[test]
+    fn entangle_hamming_weights() {
+        let mut rng = rand::thread_rng();
+        let (pk, _) = setup(&mut rng);
+
+        let zero = Identity([0u8; HASH_BYTE_LEN]);
+        let one = Identity([0xffu8; HASH_BYTE_LEN]);
+        let mut single = [0u8; HASH_BYTE_LEN];
+        single[HASH_BYTE_LEN - 1] = 0x80;
+        let single = Identity(single);
+
+        // The loop visits every parameter, regardless of the bits of the identity.
+        for v in [zero, one, single, Identity::derive_str(ID)].iter() {
+            assert_eq!(bits(&v.0).count(), CHUNKS);
+            assert_eq!(entangle(&pk, v), entangle_reference(&pk, v));
+            assert_eq!(
+                entangle_compact(&pk.compress_parameters(), v),
+                entangle(&pk, v)
+            );
+            #[cfg(feature = "alloc")]
+            assert_eq!(entangle_windowed(&window_tables(&pk), v), entangle(&pk, v));
         }
-        Parameters(res)
+
+        let all: G1Projective = pk.u.0.iter().fold(pk.uprime.into(), |acc, ui| acc + ui);
+        assert_eq!(entangle(&pk, &zero), G1Projective::from(pk.uprime));
+        assert_eq!(entangle(&pk, &one), all);
+        assert_eq!(
+            entangle(&pk, &single),
+            G1Projective::from(pk.uprime) + pk.u.0[0]
+        );
     }
-}
 
-impl Copy for Parameters {}
+    #[test]
+    fn eq_encrypt_deterministic_decrypt() {
+        let results = perform_default();
+        let seed = b"a secret seed of at least 32 bytes";
 
-impl PartialEq for Parameters {
-    fn eq(&self, rhs: &Self) -> bool {
-        self.0.iter().zip(rhs.0.iter()).all(|(x, y)| x.eq(y))
+        let c = encrypt_deterministic(&results.pk, &results.kid, &results.m, seed);
+        assert_eq!(decrypt(&results.usk, &c), results.m);
+        assert_eq!(
+            c,
+            encrypt_deterministic(&results.pk, &results.kid, &results.m, seed)
+        );
+
+        let other = Message::generate(&mut rand::thread_rng());
+        let c2 = encrypt_deterministic(&results.pk, &results.kid, &other, seed);
+        assert_ne!(c.c2, c2.c2);
+        assert_ne!(
+            c.c2,
+            encrypt_deterministic(&results.pk, &results.kid, &results.m, b"another seed").c2
+        );
     }
-}
 
-impl Default for Parameters {
-    fn default() -> Self {
-        Parameters([G1Affine::default(); CHUNKS])
+    #[cfg(feature = "std")]
+    #[test]
+    fn eq_default_rng_encrypt_decrypt() {
+        let kid = Identity::derive_str(ID);
+        let m = Message::generate(&mut rand::thread_rng());
+
+        let (pk, sk) = setup_default();
+        let usk = extract_usk_default(&pk, &sk, &kid);
+        let c = encrypt_default(&pk, &kid, &m);
+
+        assert_eq!(decrypt(&usk, &c), m);
     }
-}
 
-impl Identity {
-    /// Hash a byte slice to a set of Identity parameters, which acts as a user public key.
-    /// Uses sha3-256 internally.
-    pub fn derive(b: &[u8]) -> Identity {
-        Identity(tiny_keccak::sha3_256(b))
+    #[test]
+    fn public_key_same_generators() {
+        let results = perform_default();
+        let (other, _) = setup(&mut rand::thread_rng());
+
+        let mut mixed = results.pk;
+        mixed.uprime = other.uprime;
+        mixed.u = other.u;
+
+        assert!(results.pk.same_generators(&results.pk));
+        assert!(results.pk.same_generators(&mixed));
+        assert_ne!(results.pk, mixed);
+        assert!(!results.pk.same_generators(&other));
     }
 
-    /// Hash a string slice to a set of Identity parameters.
-    /// Directly converts characters to UTF-8 byte representation.
-    pub fn derive_str(s: &str) -> Identity {
-        Self::derive(s.as_bytes())
+    #[test]
+    fn message_combine() {
+        let results = perform_default();
+        let mask = Message::generate(&mut rand::thread_rng());
+
+        let (c1, c2, c3) = results.c.components();
+        let masked = CipherText::from_components(c1 + mask.as_gt(), *c2, *c3);
+        assert_eq!(decrypt(&results.usk, &masked), results.m.combine(&mask));
+
+        assert_eq!(Message::from_gt(*results.m.as_gt()), results.m);
+        assert_eq!(
+            results.m.combine(&Message::from_gt(Gt::identity())),
+            results.m
+        );
     }
-}
 
-impl Clone for Identity {
-    fn clone(&self) -> Self {
-        let mut res = [u8::default(); HASH_BYTE_LEN];
-        for (src, dst) in self.0.iter().zip(res.as_mut().iter_mut()) {
-            *dst = *src;
+    #[test]
+    fn identity_from_array() {
+        let digest = [7u8; HASH_BYTE_LEN];
+        let kid: Identity = digest.into();
+
+        assert_eq!(kid, Identity::from_hash_bytes(&digest));
+        assert_eq!(kid.to_hash_bytes(), digest);
+    }
+
+    #[test]
+    fn reject_identity_points() {
+        let results = perform_default();
+
+        // A malicious PKG replacing a generator with the point at infinity.
+        let mut bytes = results.pk.to_bytes();
+        bytes[96..144].copy_from_slice(&G1Affine::identity().to_compressed());
+        assert!(bool::from(PublicKey::from_bytes(&bytes).is_none()));
+        assert_eq!(
+            PublicKey::try_from_bytes(&bytes),
+            Err(DeserializeError::IdentityPoint("g1"))
+        );
+
+        let mut pk = results.pk;
+        pk.uprime = G1Affine::identity();
+        assert!(bool::from(PublicKey::from_bytes(&pk.to_bytes()).is_none()));
+        assert!(!bool::from(pk.validate()));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn entangle_tables() {
+        let results = perform_default();
+        let tables = results.pk.precompute_tables();
+
+        assert_eq!(
+            tables.entangle_identity(&results.kid),
+            results.pk.entangle_identity(&results.kid)
+        );
+        assert!(tables == *results.pk.prepare().tables());
+        assert!(tables.memory_size() > 100 * 1024);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_usk_multi_independent() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let results = perform_default();
+        let ids = [
+            results.kid,
+            Identity::derive_str("email:w.geraedts@gmail.com"),
+            Identity::derive_str("email:w.geraedts@sarif.nl"),
+        ];
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let usks = extract_usk_multi(&results.pk, &results.sk, &ids, &mut rng);
+
+        let mut rng = StdRng::seed_from_u64(42);
+        for (v, usk) in ids.iter().zip(usks.iter()) {
+            assert!(bool::from(is_valid_usk(&results.pk, v, usk)));
+            assert_eq!(&extract_usk(&results.pk, &results.sk, v, &mut rng), usk);
         }
-        Identity(res)
+        assert_eq!(decrypt(&usks[0], &results.c), results.m);
+
+        // Every key has its own randomness, even for the same identity.
+        assert_ne!(usks[0].d2, usks[1].d2);
+        assert_ne!(usks[0], usks[2]);
     }
-}
 
-impl Copy for Identity {}
+    #[test]
+    fn seal_open() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+        let payload = [42u8; 32];
+
+        let sealed = seal(&results.pk, &results.kid, &payload, &mut rng);
+        let sealed = SealedBox::from_bytes(&sealed.to_bytes()).unwrap();
+        assert_eq!(open(&results.usk, &sealed), Some(payload));
 
-impl CipherText {
-    pub fn to_bytes(&self) -> [u8; 432] {
-        let mut res = [0u8; 432];
-        let (c1, c2, c3) = mut_array_refs![&mut res, 288, 96, 48];
-        *c1 = self.c1.to_compressed();
-        *c2 = self.c2.to_compressed();
-        *c3 = self.c3.to_compressed();
-        res
+        let other = Identity::derive_str("email:someone@else.nl");
+        let usk = extract_usk(&results.pk, &results.sk, &other, &mut rng);
+        assert_eq!(open(&usk, &sealed), None);
+
+        let mut bytes = sealed.to_bytes();
+        bytes[KEM_CIPHERTEXT_BYTES] ^= 1;
+        let tampered = SealedBox::from_bytes(&bytes).unwrap();
+        assert_eq!(open(&results.usk, &tampered), None);
     }
 
-    pub fn from_bytes(bytes: &[u8; 432]) -> CtOption<Self> {
-        let (c1, c2, c3) = array_refs![bytes, 288, 96, 48];
+    #[test]
+    fn identity_to_scalar() {
+        let kid = Identity::derive_str(ID);
+        let other = Identity::derive_str("email:someone@else.nl");
 
-        let c1 = Gt::from_compressed(c1);
-        let c2 = G2Affine::from_compressed(c2);
-        let c3 = G1Affine::from_compressed(c3);
+        assert_eq!(kid.to_scalar(), kid.to_scalar());
+        assert_ne!(kid.to_scalar(), other.to_scalar());
+        // Pinned, such that the mapping stays reproducible.
+        assert_eq!(
+            std::format!("{:?}", Identity::from_hash_bytes(&[0u8; 32]).to_scalar()),
+            "0x326698c22bfbe765931dfd8af2594a9311654aa5d7c88de1e3097214ae619983"
+        );
+    }
 
-        c1.and_then(|c1| c2.and_then(|c2| c3.map(|c3| CipherText { c1, c2, c3 })))
+    #[test]
+    fn ciphertext_well_formed() {
+        let results = perform_default();
+        let other = Identity::derive_str("email:someone@else.nl");
+
+        assert!(results.c.is_well_formed(&results.pk, &results.kid));
+        assert!(!results.c.is_well_formed(&results.pk, &other));
+
+        let mut c = results.c;
+        c.c3 = (G1Projective::from(c.c3) + G1Affine::generator()).into();
+        assert!(!c.is_well_formed(&results.pk, &results.kid));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn derive_blake3() {
+        let kid = Identity::derive_blake3(b"");
 
-    const ID: &'static str = "email:w.geraedts@sarif.nl";
+        // The BLAKE3 digest of the empty input.
+        assert_eq!(
+            std::format!("{:02x?}", &kid.as_bytes()[..4]),
+            "[af, 13, 49, b9]"
+        );
+        assert_ne!(
+            Identity::derive_blake3(ID.as_bytes()),
+            Identity::derive_str(ID)
+        );
+    }
 
-    #[allow(dead_code)]
-    struct DefaultSubResults {
-        kid: Identity,
-        m: Message,
-        pk: PublicKey,
-        sk: SecretKey,
-        usk: UserSecretKey,
-        c: CipherText,
+    #[test]
+    fn domain_separation_tags() {
+        let b = ID.as_bytes();
+        assert_eq!(
+            Identity::derive_with_domain(EMAIL_DOMAIN, b).0,
+            sha3_256_dst(EMAIL_DOMAIN, &[b])
+        );
+
+        let long = [0u8; 256];
+        assert_ne!(sha3_256_dst(&long, &[]), sha3_256_dst(&[], &[&long]));
     }
 
-    fn perform_default() -> DefaultSubResults {
-        let mut rng = rand::thread_rng();
+    #[test]
+    fn derive_incremental() {
+        let mut h = IdentityHasher::new();
+        for part in ID.as_bytes().chunks(5) {
+            h.update(part);
+        }
+        assert_eq!(h.finalize().0, Identity::derive_str(ID).0);
 
-        let id = ID.as_bytes();
-        let kid = Identity::derive(id);
+        let mut h = IdentityHasher::with_domain(b"app");
+        h.update(&ID.as_bytes()[..3]);
+        h.update(&ID.as_bytes()[3..]);
+        assert_eq!(
+            h.finalize().0,
+            Identity::derive_with_domain(b"app", ID.as_bytes()).0
+        );
+    }
 
-        let m = Message::generate(&mut rng);
+    #[test]
+    fn derive_from_hash_bytes() {
+        let digest = tiny_keccak::sha3_256(ID.as_bytes());
+        let v = Identity::from_hash_bytes(&digest);
+
+        assert_eq!(
+            v.to_hash_bytes(),
+            Identity::derive(ID.as_bytes()).to_hash_bytes()
+        );
+        assert_ne!(
+            Identity::derive_shake(ID.as_bytes()).to_hash_bytes(),
+            v.to_hash_bytes()
+        );
+
+        // A PKG given the digest extracts a key for the same identity.
+        let results = perform_default();
+        let usk = extract_usk(&results.pk, &results.sk, &v, &mut rand::thread_rng());
+        assert_eq!(decrypt(&usk, &results.c), results.m);
+    }
+
+    #[test]
+    fn eq_display_from_str() {
+        use crate::error::{DeserializeError, ParseError};
+        use std::string::ToString;
+
+        let result = perform_default();
+
+        let s = result.c.to_string();
+        assert!(!s.contains('=') && !s.contains('+') && !s.contains('/'));
+        assert_eq!(result.c, s.parse().unwrap());
+        assert!(result.pk == result.pk.to_string().parse().unwrap());
+        assert_eq!(result.usk, result.usk.to_string().parse().unwrap());
+
+        assert_eq!(
+            s[1..].parse::<CipherText>(),
+            Err(ParseError::Length {
+                expected: 432,
+                found: 431
+            })
+        );
+        assert_eq!(
+            std::format!("!{}", &s[1..]).parse::<CipherText>(),
+            Err(ParseError::Base64)
+        );
+        assert_eq!(
+            "A".repeat(s.len()).parse::<CipherText>(),
+            Err(ParseError::Deserialize(DeserializeError::GtPoint("c1")))
+        );
+    }
+
+    /// Identities for which known-answer vectors are generated, with the seed for each vector.
+    fn known_answer_cases() -> std::vec::Vec<([u8; 32], std::string::String)> {
+        use std::string::ToString;
+
+        vec![
+            ([0u8; 32], "".to_string()),
+            ([1u8; 32], ID.to_string()),
+            (
+                [2u8; 32],
+                std::format!("email:{}@sarif.nl", "long".repeat(256)),
+            ),
+        ]
+    }
+
+    /// Deterministically generate a full known-answer vector using `StdRng` seeded with `seed`,
+    /// sampling in the order setup, extract_usk, generate message and encrypt.
+    fn known_answer(seed: [u8; 32], id: &str) -> serde_json::Value {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        fn hex(bytes: &[u8]) -> std::string::String {
+            bytes.iter().map(|b| std::format!("{:02x}", b)).collect()
+        }
 
+        let mut rng = StdRng::from_seed(seed);
+
+        let kid = Identity::derive_str(id);
         let (pk, sk) = setup(&mut rng);
         let usk = extract_usk(&pk, &sk, &kid, &mut rng);
-
+        let m = Message::generate(&mut rng);
         let c = encrypt(&pk, &kid, &m, &mut rng);
 
-        DefaultSubResults {
-            kid,
-            m,
-            pk,
-            sk,
-            usk,
-            c,
-        }
+        assert_eq!(m, decrypt(&usk, &c));
+
+        serde_json::json!({
+            "seed": hex(&seed),
+            "identity": id,
+            "pk": hex(&pk.to_bytes()),
+            "sk": hex(&sk.to_bytes()),
+            "usk": hex(&usk.to_bytes()),
+            "m": hex(&m.to_bytes()),
+            "c": hex(&c.to_bytes()),
+        })
+    }
+
+    fn known_answers() -> serde_json::Value {
+        known_answer_cases()
+            .iter()
+            .map(|(seed, id)| known_answer(*seed, id))
+            .collect()
     }
 
     #[test]
-    fn eq_encrypt_decrypt() {
-        let results = perform_default();
-        let m2 = decrypt(&results.usk, &results.c);
+    fn known_answer_vectors() {
+        let expected: serde_json::Value =
+            serde_json::from_str(include_str!("../testdata/waters_kat.json")).unwrap();
 
-        assert_eq!(results.m, m2);
+        assert!(expected == known_answers());
     }
 
+    /// Regenerate the known-answer vectors, only needed when the serialization changes.
     #[test]
-    fn eq_serialize_deserialize() {
+    #[ignore]
+    fn generate_known_answer_vectors() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/waters_kat.json");
+        let json = serde_json::to_string_pretty(&known_answers()).unwrap();
+        std::fs::write(path, json + "\n").unwrap();
+    }
+
+    #[test]
+    fn try_from_slice() {
+        use crate::error::{DeserializeError, ParseError};
+        use core::convert::TryFrom;
+
         let result = perform_default();
+        let bytes = result.c.to_bytes();
 
-        assert_eq!(result.m, Message::from_bytes(&result.m.to_bytes()).unwrap());
-        assert!(result.pk == PublicKey::from_bytes(&result.pk.to_bytes()).unwrap());
+        assert_eq!(result.c, CipherText::try_from(&bytes[..]).unwrap());
+        assert_eq!(
+            result.m,
+            Message::try_from(&result.m.to_bytes()[..]).unwrap()
+        );
         assert_eq!(
             result.sk,
-            SecretKey::from_bytes(&result.sk.to_bytes()).unwrap()
+            SecretKey::try_from(&result.sk.to_bytes()[..]).unwrap()
         );
+        assert!(result.pk == PublicKey::try_from(&result.pk.to_bytes()[..]).unwrap());
         assert_eq!(
             result.usk,
-            UserSecretKey::from_bytes(&result.usk.to_bytes()).unwrap()
+            UserSecretKey::try_from(&result.usk.to_bytes()[..]).unwrap()
         );
+
         assert_eq!(
-            result.c,
-            CipherText::from_bytes(&result.c.to_bytes()).unwrap()
+            CipherText::try_from(&bytes[1..]),
+            Err(ParseError::Length {
+                expected: 432,
+                found: 431
+            })
+        );
+        assert_eq!(
+            CipherText::try_from(&[0u8; 432][..]),
+            Err(ParseError::Deserialize(DeserializeError::GtPoint("c1")))
+        );
+    }
+
+    #[test]
+    fn try_deserialize() {
+        let result = perform_default();
+
+        assert!(result.pk == PublicKey::try_from_bytes(&result.pk.to_bytes()).unwrap());
+        assert_eq!(
+            result.usk,
+            UserSecretKey::try_from_bytes(&result.usk.to_bytes()).unwrap()
+        );
+
+        let mut bytes = result.c.to_bytes();
+        bytes[288] ^= 0xff;
+        assert_eq!(
+            CipherText::try_from_bytes(&bytes),
+            Err(DeserializeError::NonCanonical("c2"))
+        );
+    }
+
+    #[test]
+    fn try_from_bytes_reasons() {
+        let generator = G1Affine::generator().to_compressed();
+
+        // Not compressed, or the point at infinity with a nonzero x-coordinate.
+        let mut bytes = generator;
+        bytes[0] &= 0x7f;
+        assert_eq!(
+            try_g1(&bytes, "p"),
+            Err(DeserializeError::NonCanonical("p"))
+        );
+        let mut bytes = [0u8; 48];
+        bytes[0] = 0xc0;
+        bytes[47] = 1;
+        assert_eq!(
+            try_g1(&bytes, "p"),
+            Err(DeserializeError::NonCanonical("p"))
+        );
+
+        // An x-coordinate that is not reduced modulo the field prime.
+        let mut bytes = [0xffu8; 48];
+        bytes[0] = 0x9f;
+        assert_eq!(
+            try_g1(&bytes, "p"),
+            Err(DeserializeError::NonCanonical("p"))
+        );
+
+        // Find x-coordinates without a point on the curve, and with a point outside the subgroup.
+        let mut off_curve = None;
+        let mut wrong_subgroup = None;
+        for i in 1..=255u8 {
+            let mut bytes = [0u8; 48];
+            bytes[0] = 0x80;
+            bytes[47] = i;
+            match bool::from(G1Affine::from_compressed_unchecked(&bytes).is_some()) {
+                false => off_curve = off_curve.or(Some(bytes)),
+                true => wrong_subgroup = wrong_subgroup.or(Some(bytes)),
+            }
+        }
+        assert_eq!(
+            try_g1(&off_curve.unwrap(), "p"),
+            Err(DeserializeError::OffCurve("p"))
+        );
+        assert_eq!(
+            try_g1(&wrong_subgroup.unwrap(), "p"),
+            Err(DeserializeError::WrongSubgroup("p"))
         );
     }
 }
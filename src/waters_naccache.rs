@@ -10,7 +10,7 @@
 //! All operations in this library are implemented to run in constant time.
 
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
-use rand::Rng;
+use rand::{CryptoRng, RngCore};
 use subtle::{Choice, ConditionallySelectable, CtOption};
 
 use crate::util::*;
@@ -33,17 +33,18 @@ impl ConditionallySelectable for Parameters {
     fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
         let mut res = [G2Affine::default(); CHUNKS];
         for (i, (ai, bi)) in a.0.iter().zip(b.0.iter()).enumerate() {
-            res[i] = G2Affine::conditional_select(&ai, &bi, choice);
+            res[i] = G2Affine::conditional_select(ai, bi, choice);
         }
         Parameters(res)
     }
 }
 
 impl Parameters {
+    #[allow(clippy::wrong_self_convention)]
     pub fn to_bytes(&self) -> [u8; PARAMETERSIZE] {
         let mut res = [0u8; PARAMETERSIZE];
-        for i in 0..CHUNKS {
-            *array_mut_ref![&mut res, i * 96, 96] = self.0[i].to_compressed();
+        for (i, p) in self.0.iter().enumerate() {
+            *array_mut_ref![&mut res, i * 96, 96] = p.to_compressed();
         }
         res
     }
@@ -51,10 +52,10 @@ impl Parameters {
     pub fn from_bytes(bytes: &[u8; PARAMETERSIZE]) -> CtOption<Self> {
         let mut res = [G2Affine::default(); CHUNKS];
         let mut is_some = Choice::from(1u8);
-        for i in 0..CHUNKS {
+        for (i, ri) in res.iter_mut().enumerate() {
             is_some &= G2Affine::from_compressed(array_ref![bytes, i * 96, 96])
                 .map(|s| {
-                    res[i] = s;
+                    *ri = s;
                 })
                 .is_some();
         }
@@ -197,7 +198,7 @@ pub struct Message(Gt);
 
 impl Message {
     /// Generate a random point on the paired curve.
-    pub fn generate<R: Rng>(rng: &mut R) -> Self {
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
         Self(rand_gt(rng))
     }
 
@@ -210,8 +211,14 @@ impl Message {
     }
 }
 
+/// The Waters-Naccache scheme, implementing the common `IbeScheme` interface.
+#[derive(Clone, Copy, Debug)]
+pub struct WatersNaccache;
+
+impl_ibe_scheme!(WatersNaccache);
+
 /// Generate a keypair used by the Private Key Generator (PKG).
-pub fn setup<R: Rng>(rng: &mut R) -> (PublicKey, SecretKey) {
+pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
     let g: G1Affine = rand_g1(rng).into();
 
     let alpha = rand_scalar(rng);
@@ -251,7 +258,7 @@ fn entangle(pk: &PublicKey, v: &Identity) -> G2Projective {
 }
 
 /// Extract an user secret key for a given identity.
-pub fn extract_usk<R: Rng>(
+pub fn extract_usk<R: RngCore + CryptoRng>(
     pk: &PublicKey,
     sk: &SecretKey,
     v: &Identity,
@@ -266,7 +273,12 @@ pub fn extract_usk<R: Rng>(
 }
 
 /// Encrypt a message using the PKG public key and an identity.
-pub fn encrypt<R: Rng>(pk: &PublicKey, v: &Identity, m: &Message, rng: &mut R) -> CipherText {
+pub fn encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
     let t = rand_scalar(rng);
 
     let c3coll = entangle(pk, v);
@@ -316,7 +328,7 @@ impl Identity {
 mod tests {
     use super::*;
 
-    const ID: &'static str = "email:w.geraedts@sarif.nl";
+    const ID: &str = "email:w.geraedts@sarif.nl";
 
     #[allow(dead_code)]
     struct DefaultSubResults {
@@ -361,7 +373,7 @@ mod tests {
 
     #[test]
     fn stability_identity() {
-        const REFERENCE: &'static [u32; 16] = &[
+        const REFERENCE: &[u32; 16] = &[
             224058892, 3543031066, 2100894308, 1450993543, 380724969, 4144530249, 2749396120,
             320408521, 409248772, 2464563459, 877936958, 2596797041, 3979538376, 3505820338,
             590474010, 189115610,